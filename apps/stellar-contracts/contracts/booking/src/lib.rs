@@ -1,342 +1,1526 @@
 #![no_std]
 
+use common::booking::{
+    Availability, Block, Booking, BookingError, BookingRules, BookingStatus, CancellationPolicy,
+    Config, DataKey, Deposit, DepositPolicy, DepositStatus, Dispute, PropertyCalendar,
+    PropertyPricing, Quote, Settlement, BPS_DENOMINATOR, CHECK_IN_WINDOW, COMPLETION_GRACE, DAY,
+    MAX_BLOCKS, MAX_POLICY_TIERS, MAX_QUOTE_NIGHTS,
+};
+use common::listing::{PropertyListing, PropertyListingClient, PropertyStatus};
+use common::storage::{BUMP_AMOUNT, LIFETIME_THRESHOLD};
+use common::PropertyId;
 use soroban_sdk::{
-    contract, contractimpl, contracttype, symbol_short, vec, Address, Env, String, Symbol, Vec,
+    contract, contractimpl, symbol_short, token, Address, BytesN, Env, IntoVal, String, Symbol,
+    TryFromVal, Val, Vec,
 };
 
-// Booking status enum
-#[contracttype]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub enum BookingStatus {
-    Pending,
-    Confirmed,
-    Completed,
-    Cancelled,
-}
+// Event names. Every booking event is published with the topics
+// (name, booking_id, property_id, guest).
+const EVT_CREATED: Symbol = symbol_short!("created");
+const EVT_CONFIRMED: Symbol = symbol_short!("confirmed");
+const EVT_COMPLETED: Symbol = symbol_short!("completed");
+const EVT_CANCELLED: Symbol = symbol_short!("cancelled");
+const EVT_ESCROW_LINKED: Symbol = symbol_short!("esc_link");
+const EVT_REFUNDED: Symbol = symbol_short!("refunded");
+const EVT_EXPIRED: Symbol = symbol_short!("expired");
+const EVT_FEE_PAID: Symbol = symbol_short!("fee_paid");
+const EVT_DEPOSIT_CLAIMED: Symbol = symbol_short!("dep_claim");
+const EVT_DEPOSIT_DISPUTED: Symbol = symbol_short!("dep_disp");
+const EVT_DEPOSIT_SETTLED: Symbol = symbol_short!("dep_settl");
+const EVT_DISPUTED: Symbol = symbol_short!("disputed");
+const EVT_RESOLVED: Symbol = symbol_short!("resolved");
+const EVT_CHECKED_IN: Symbol = symbol_short!("checkin");
+const EVT_CHECKED_OUT: Symbol = symbol_short!("checkout");
 
-// Main booking data structure
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Booking {
-    pub id: u64, // Changed to u64 for simplicity
-    pub property_id: String,
-    pub user_id: String,
-    pub start_date: u64,
-    pub end_date: u64,
-    pub total_price: i128,
-    pub status: BookingStatus,
-    pub escrow_id: Option<String>,
-}
+// Calendar block event names, published with the topics (name, property_id)
+const EVT_BLOCKED: Symbol = symbol_short!("blocked");
+const EVT_UNBLOCKED: Symbol = symbol_short!("unblocked");
 
-// Storage keys
-const BOOKINGS: Symbol = symbol_short!("BOOKINGS");
-const BOOK_COUNT: Symbol = symbol_short!("BOOKCOUNT");
+// How long a Pending booking holds its dates when no hold period has been set
+const DEFAULT_HOLD_PERIOD: u64 = DAY;
 
 #[contract]
 pub struct BookingContract;
 
 #[contractimpl]
 impl BookingContract {
-    /// Initialize the contract
-    pub fn initialize(env: Env) {
-        env.storage().persistent().set(&BOOK_COUNT, &0u64);
+    /// Initialize the contract with its admin, the SEP-41 token (e.g. USDC) used for
+    /// payments, the property listing contract and the platform fee settings. Can only
+    /// run once. No guest fee is charged until one is set with `set_config`.
+    pub fn initialize(
+        env: Env,
+        admin: Address,
+        token: Address,
+        listing_contract: Address,
+        fee_bps: u32,
+        treasury: Address,
+    ) -> Result<(), BookingError> {
+        // Running again would reset the counter and reissue booking IDs
+        if env.storage().instance().has(&DataKey::Config) {
+            return Err(BookingError::AlreadyInitialized);
+        }
+        admin.require_auth();
+
+        let config = Config {
+            admin,
+            token,
+            listing_contract,
+            fee_bps,
+            guest_fee_bps: 0,
+            treasury,
+        };
+        Self::validate_config(&config)?;
+
+        env.storage().instance().set(&DataKey::Config, &config);
+        env.storage().instance().set(&DataKey::BookingCount, &0u64);
+        Self::extend_instance_ttl(&env);
+
+        Ok(())
     }
 
-    /// Check if a property is available for the given dates
-    pub fn check_availability(
-        env: Env,
-        property_id: String,
-        start_date: u64,
-        end_date: u64,
-    ) -> bool {
-        // Validate dates
-        if start_date >= end_date {
-            return false;
-        }
-
-        // Get all bookings for this property
-        let bookings = Self::get_property_bookings_internal(&env, property_id.clone());
-
-        // Check for overlaps
-        for booking in bookings.iter() {
-            if booking.status != BookingStatus::Cancelled {
-                // Check if dates overlap
-                if !(end_date <= booking.start_date || start_date >= booking.end_date) {
-                    return false;
-                }
-            }
+    /// Get the contract configuration
+    pub fn get_config(env: Env) -> Result<Config, BookingError> {
+        Self::config_internal(&env)
+    }
+
+    /// Replace the contract configuration, admin only. The payment token cannot be
+    /// changed because escrowed balances are held in it.
+    pub fn set_config(env: Env, config: Config) -> Result<bool, BookingError> {
+        let current = Self::config_internal(&env)?;
+        current.admin.require_auth();
+
+        if config.token != current.token {
+            return Err(BookingError::InvalidConfig);
+        }
+        Self::validate_config(&config)?;
+
+        env.storage().instance().set(&DataKey::Config, &config);
+        Self::extend_instance_ttl(&env);
+        Ok(true)
+    }
+
+    /// Get the admin address
+    pub fn get_admin(env: Env) -> Result<Address, BookingError> {
+        Self::admin_internal(&env)
+    }
+
+    /// Set the operator (system account) allowed to complete bookings, admin only
+    pub fn set_operator(env: Env, operator: Address) -> Result<bool, BookingError> {
+        Self::admin_internal(&env)?.require_auth();
+        env.storage().instance().set(&DataKey::Operator, &operator);
+        Self::extend_instance_ttl(&env);
+        Ok(true)
+    }
+
+    /// Get the operator address, if one is set
+    pub fn get_operator(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::Operator)
+    }
+
+    /// Set the panel of arbitrators who resolve disputes, admin only. Any one of them
+    /// can resolve a dispute; while the panel is empty the admin arbitrates.
+    pub fn set_arbitrators(env: Env, arbitrators: Vec<Address>) -> Result<bool, BookingError> {
+        Self::admin_internal(&env)?.require_auth();
+        env.storage()
+            .instance()
+            .set(&DataKey::Arbitrators, &arbitrators);
+        Self::extend_instance_ttl(&env);
+        Ok(true)
+    }
+
+    /// Get the panel of arbitrators
+    pub fn get_arbitrators(env: Env) -> Vec<Address> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Arbitrators)
+            .unwrap_or(Vec::new(&env))
+    }
+
+    /// Set how long, in seconds, a Pending booking holds its dates before it can be
+    /// expired, admin only. Applies to bookings created afterwards.
+    pub fn set_hold_period(env: Env, hold_period: u64) -> Result<bool, BookingError> {
+        Self::admin_internal(&env)?.require_auth();
+        if hold_period == 0 {
+            return Err(BookingError::InvalidConfig);
         }
+        env.storage()
+            .instance()
+            .set(&DataKey::HoldPeriod, &hold_period);
+        Self::extend_instance_ttl(&env);
+        Ok(true)
+    }
+
+    /// Get the hold period applied to new Pending bookings
+    pub fn get_hold_period(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::HoldPeriod)
+            .unwrap_or(DEFAULT_HOLD_PERIOD)
+    }
 
-        true
+    /// Get the payment token address
+    pub fn get_token(env: Env) -> Result<Address, BookingError> {
+        Self::token_internal(&env)
     }
 
-    /// Create a new booking
+    /// Check if a property can be booked from night `check_in_day` to
+    /// `check_out_day` (in the property's local days since epoch), returning the
+    /// reason if it can't
+    pub fn check_availability(
+        env: Env,
+        property_id: PropertyId,
+        check_in_day: u32,
+        check_out_day: u32,
+    ) -> Availability {
+        Self::extend_instance_ttl(&env);
+        let calendar = Self::get_calendar(env.clone(), property_id.clone());
+        Self::availability_internal(&env, &property_id, &calendar, check_in_day, check_out_day)
+    }
+
+    /// Create a new booking from night `check_in_day` to `check_out_day`. The
+    /// property must be listed in the property listing contract and not under
+    /// maintenance; its owner is the host. `total_price` must match the property's
+    /// quote for the stay; the guest also pays the platform's guest fee and the
+    /// property's security deposit on top.
     pub fn create_booking(
         env: Env,
-        property_id: String,
-        user_id: String,
-        start_date: u64,
-        end_date: u64,
+        property_id: PropertyId,
+        guest: Address,
+        check_in_day: u32,
+        check_out_day: u32,
+        guests: u32,
         total_price: i128,
-    ) -> u64 {
-        // Validate inputs
-        if start_date >= end_date {
-            panic!("Invalid dates: start date must be before end date");
+    ) -> Result<u64, BookingError> {
+        guest.require_auth();
+
+        // Validate inputs: check-in must be before check-out
+        if check_in_day >= check_out_day {
+            return Err(BookingError::InvalidDates);
         }
 
+        // Price must be greater than zero
         if total_price <= 0 {
-            panic!("Invalid price: price must be greater than zero");
+            return Err(BookingError::InvalidPrice);
         }
 
-        // Get current timestamp for basic validation
+        // Check-in cannot be in the past
         let current_time = env.ledger().timestamp();
+        let calendar = Self::get_calendar(env.clone(), property_id.clone());
+        let start_date = calendar.check_in_time(check_in_day);
         if start_date < current_time {
-            panic!("Invalid dates: start date cannot be in the past");
+            return Err(BookingError::InvalidDates);
+        }
+
+        // The listing must exist and accept bookings; its owner receives the payout
+        let listing = Self::listing_internal(&env, &property_id)?;
+        if listing.status == PropertyStatus::Maintenance {
+            return Err(BookingError::PropertyUnavailable);
         }
 
-        // Check availability
-        if !Self::check_availability(env.clone(), property_id.clone(), start_date, end_date) {
-            panic!("Booking overlap: dates conflict with existing reservation");
+        // Check availability and the host's booking rules
+        let availability =
+            Self::availability_internal(&env, &property_id, &calendar, check_in_day, check_out_day);
+        if let Some(error) = availability.to_error() {
+            return Err(error);
         }
 
-        // Generate unique booking ID
-        let book_count: u64 = env.storage().persistent().get(&BOOK_COUNT).unwrap_or(0);
+        // The price must be what the property's pricing charges for the stay
+        let quote = Self::quote(
+            env.clone(),
+            property_id.clone(),
+            check_in_day,
+            check_out_day,
+            guests,
+        )?;
+        if total_price != quote.total {
+            return Err(BookingError::PriceMismatch);
+        }
+
+        // Generate unique booking ID. A missing counter means the contract was
+        // never initialized; it must not silently restart from zero.
+        let book_count: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::BookingCount)
+            .ok_or(BookingError::NotInitialized)?;
 
         let booking_id = book_count;
 
+        // Snapshot the platform fees in force now
+        let config = Self::config_internal(&env)?;
+        let guest_fee = total_price * config.guest_fee_bps as i128 / BPS_DENOMINATOR as i128;
+
         // Create booking
         let booking = Booking {
             id: booking_id,
             property_id: property_id.clone(),
-            user_id: user_id.clone(),
+            guest: guest.clone(),
+            host: listing.owner,
+            check_in_day,
+            check_out_day,
+            guests,
             start_date,
-            end_date,
+            end_date: calendar.check_out_time(check_out_day),
             total_price,
+            guest_fee,
+            fee_bps: config.fee_bps,
             status: BookingStatus::Pending,
-            escrow_id: None, // Will be set when escrow is initiated
+            escrow_id: None, // Set when an external escrow reference is linked
+            escrow_balance: total_price + guest_fee,
+            cancellation_policy: Self::get_cancellation_policy(env.clone(), property_id.clone()),
+            expires_at: Some(current_time + Self::get_hold_period(env.clone())),
+            deposit: Self::get_deposit_policy(env.clone(), property_id.clone())
+                .map_or(Deposit::none(), |policy| Deposit::held(&policy)),
+            check_in_release_bps: Self::get_check_in_release(env.clone(), property_id.clone()),
+            checked_in_at: None,
         };
 
-        // Store booking
-        let mut bookings_map: Vec<(u64, Booking)> = env
-            .storage()
-            .persistent()
-            .get(&BOOKINGS)
-            .unwrap_or(vec![&env]);
-
-        bookings_map.push_back((booking_id, booking.clone()));
-        env.storage().persistent().set(&BOOKINGS, &bookings_map);
+        // Pull the payment and any security deposit from the guest into contract custody
+        Self::token_client(&env)?.transfer(
+            &guest,
+            &env.current_contract_address(),
+            &(booking.escrow_balance + booking.deposit.amount),
+        );
 
-        // Update property bookings
-        let mut prop_bookings = Self::get_property_bookings_internal(&env, property_id.clone());
-        prop_bookings.push_back(booking.clone());
+        // Store booking
+        Self::save_booking(&env, &booking);
 
-        // Store with property ID as key
-        env.storage().persistent().set(&property_id, &prop_bookings);
+        // Update property bookings index
+        Self::index_booking(&env, &booking);
 
         // Update booking counter
         env.storage()
-            .persistent()
-            .set(&BOOK_COUNT, &(book_count + 1));
+            .instance()
+            .set(&DataKey::BookingCount, &(book_count + 1));
 
-        // TODO: Initiate escrow here
-        // This would typically involve calling an external escrow contract
+        Self::publish_event(
+            &env,
+            EVT_CREATED,
+            &booking,
+            (
+                booking.host.clone(),
+                booking.start_date,
+                booking.end_date,
+                booking.total_price,
+            ),
+        );
 
-        booking_id
+        Ok(booking_id)
     }
 
     /// Cancel a booking
-    pub fn cancel_booking(env: Env, booking_id: u64, user_id: String) -> bool {
-        // Get the booking
-        let mut bookings_map: Vec<(u64, Booking)> = env
-            .storage()
-            .persistent()
-            .get(&BOOKINGS)
-            .unwrap_or(vec![&env]);
-
-        let mut booking_found = false;
-        let mut updated_booking = None;
-
-        for i in 0..bookings_map.len() {
-            let (id, mut booking) = bookings_map.get(i).unwrap();
-            if id == booking_id {
-                // Verify user authorization
-                if booking.user_id != user_id {
-                    panic!("Unauthorized: only the booking owner can cancel");
-                }
+    pub fn cancel_booking(env: Env, booking_id: u64, guest: Address) -> Result<bool, BookingError> {
+        guest.require_auth();
 
-                // Check if booking can be cancelled
-                if booking.status == BookingStatus::Completed
-                    || booking.status == BookingStatus::Cancelled
-                {
-                    panic!("Invalid status: booking cannot be cancelled");
-                }
+        let mut booking = Self::load_booking(&env, booking_id)?;
 
-                // Update status
-                booking.status = BookingStatus::Cancelled;
-                bookings_map.set(i, (id, booking.clone()));
-                updated_booking = Some(booking.clone());
-                booking_found = true;
-                break;
-            }
+        // Verify user authorization
+        if booking.guest != guest {
+            return Err(BookingError::Unauthorized);
         }
 
-        if !booking_found {
-            panic!("Booking not found");
+        // Escrow is frozen while disputed
+        Self::ensure_not_disputed(&booking)?;
+
+        // Check if booking can be cancelled
+        if matches!(
+            booking.status,
+            BookingStatus::Completed
+                | BookingStatus::Cancelled
+                | BookingStatus::Expired
+                | BookingStatus::CheckedIn
+        ) {
+            return Err(BookingError::InvalidTransition);
         }
 
-        // Update storage
-        env.storage().persistent().set(&BOOKINGS, &bookings_map);
+        // Guests can only cancel before the stay starts
+        if env.ledger().timestamp() >= booking.start_date {
+            return Err(BookingError::InvalidTransition);
+        }
 
-        // Update property bookings
-        if let Some(booking) = updated_booking {
-            let mut prop_bookings: Vec<Booking> = env
-                .storage()
-                .persistent()
-                .get(&booking.property_id)
-                .unwrap_or(vec![&env]);
+        // Update status and refund escrowed funds: in full while the host has not
+        // confirmed, otherwise per the cancellation policy
+        let refund = if booking.status == BookingStatus::Pending {
+            booking.escrow_balance
+        } else {
+            Self::cancellation_refund(&env, &booking)
+        };
+        booking.status = BookingStatus::Cancelled;
+        Self::refund_escrow(&env, &mut booking, refund)?;
 
-            for i in 0..prop_bookings.len() {
-                if prop_bookings.get(i).unwrap().id == booking_id {
-                    prop_bookings.set(i, booking.clone());
-                    break;
-                }
+        Self::save_booking(&env, &booking);
+        Self::unindex_booking(&env, &booking);
+
+        Self::publish_event(&env, EVT_CANCELLED, &booking, (guest,));
+
+        Ok(true)
+    }
+
+    /// Get a specific booking by ID
+    pub fn get_booking(env: Env, booking_id: u64) -> Result<Booking, BookingError> {
+        Self::extend_instance_ttl(&env);
+        Self::load_booking(&env, booking_id)
+    }
+
+    /// Get where a booking's payment has gone so far: refunded to the guest, paid
+    /// to the host and taken as platform fees, and what is still held in escrow
+    pub fn get_settlement(env: Env, booking_id: u64) -> Result<Settlement, BookingError> {
+        let booking = Self::load_booking(&env, booking_id)?;
+        Ok(Self::settlement_internal(&env, &booking))
+    }
+
+    /// Update booking status
+    pub fn update_status(
+        env: Env,
+        booking_id: u64,
+        new_status: BookingStatus,
+        caller: Address,
+    ) -> Result<Booking, BookingError> {
+        caller.require_auth();
+
+        let mut booking = Self::load_booking(&env, booking_id)?;
+
+        // Escrow is frozen while disputed
+        Self::ensure_not_disputed(&booking)?;
+
+        // Verify the caller's role allows this transition
+        if !Self::can_update_status(&env, &booking, &caller, new_status)? {
+            return Err(BookingError::Unauthorized);
+        }
+
+        // Validate status transition
+        match (booking.status, new_status) {
+            (BookingStatus::Pending, BookingStatus::Confirmed)
+            | (BookingStatus::Confirmed, BookingStatus::Completed)
+            | (BookingStatus::CheckedIn, BookingStatus::Completed)
+            | (BookingStatus::Pending, BookingStatus::Cancelled)
+            | (BookingStatus::Confirmed, BookingStatus::Cancelled) => {}
+            _ => return Err(BookingError::InvalidTransition),
+        }
+
+        // A booking whose hold has run out can only be expired or cancelled
+        if new_status == BookingStatus::Confirmed && booking.is_expired(env.ledger().timestamp()) {
+            return Err(BookingError::HoldExpired);
+        }
+
+        // Stays complete at check-out, or once the grace period after it has passed
+        // without a dispute; only the admin can complete one sooner
+        if new_status == BookingStatus::Completed
+            && caller != Self::admin_internal(&env)?
+            && env.ledger().timestamp() < booking.end_date + COMPLETION_GRACE
+        {
+            return Err(BookingError::GracePeriodActive);
+        }
+
+        // Guests can only cancel before the stay starts
+        if new_status == BookingStatus::Cancelled
+            && caller == booking.guest
+            && env.ledger().timestamp() >= booking.start_date
+        {
+            return Err(BookingError::InvalidTransition);
+        }
+
+        let previous_status = booking.status;
+        booking.status = new_status;
+
+        // Move escrowed funds, persist and publish the event for the new status
+        match new_status {
+            BookingStatus::Confirmed => {
+                booking.expires_at = None;
+                Self::save_booking(&env, &booking);
+                Self::publish_event(&env, EVT_CONFIRMED, &booking, (caller,));
             }
+            BookingStatus::Completed => {
+                let payout = Self::release_escrow(&env, &mut booking)?;
+                Self::save_booking(&env, &booking);
+                Self::publish_event(&env, EVT_COMPLETED, &booking, (caller, payout));
+            }
+            BookingStatus::Cancelled => {
+                // Guests are bound by the cancellation policy once the host has
+                // confirmed; Pending bookings and host or admin cancellations
+                // refund in full
+                let refund =
+                    if caller == booking.guest && previous_status == BookingStatus::Confirmed {
+                        Self::cancellation_refund(&env, &booking)
+                    } else {
+                        booking.escrow_balance
+                    };
+                Self::refund_escrow(&env, &mut booking, refund)?;
+                Self::save_booking(&env, &booking);
+                Self::unindex_booking(&env, &booking);
+                Self::publish_event(&env, EVT_CANCELLED, &booking, (caller,));
+            }
+            BookingStatus::Pending
+            | BookingStatus::Expired
+            | BookingStatus::Disputed
+            | BookingStatus::CheckedIn => {}
+        }
 
-            env.storage()
-                .persistent()
-                .set(&booking.property_id, &prop_bookings);
+        Ok(booking)
+    }
+
+    /// Expire a Pending booking whose hold has run out, refunding its escrow to the
+    /// guest in full. Anyone can call this.
+    pub fn expire_booking(env: Env, booking_id: u64) -> Result<Booking, BookingError> {
+        let mut booking = Self::load_booking(&env, booking_id)?;
+
+        if booking.status != BookingStatus::Pending {
+            return Err(BookingError::InvalidTransition);
         }
+        if !booking.is_expired(env.ledger().timestamp()) {
+            return Err(BookingError::HoldActive);
+        }
+
+        booking.status = BookingStatus::Expired;
+        let refund = booking.escrow_balance;
+        Self::refund_escrow(&env, &mut booking, refund)?;
+        Self::save_booking(&env, &booking);
+        Self::unindex_booking(&env, &booking);
 
-        // TODO: Trigger escrow refund
+        Self::publish_event(&env, EVT_EXPIRED, &booking, (booking.expires_at,));
 
-        true
+        Ok(booking)
     }
 
-    /// Get a specific booking by ID
-    pub fn get_booking(env: Env, booking_id: u64) -> Booking {
-        let bookings_map: Vec<(u64, Booking)> = env
-            .storage()
-            .persistent()
-            .get(&BOOKINGS)
-            .unwrap_or(vec![&env]);
+    /// Check in to a confirmed booking, guest only, within `CHECK_IN_WINDOW` of the
+    /// check-in time. Releases the share of the payment the property's check-in
+    /// release allows to the host.
+    pub fn check_in(env: Env, booking_id: u64) -> Result<Booking, BookingError> {
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        booking.guest.require_auth();
 
-        for (id, booking) in bookings_map.iter() {
-            if id == booking_id {
-                return booking;
-            }
+        Self::ensure_not_disputed(&booking)?;
+        if booking.status != BookingStatus::Confirmed {
+            return Err(BookingError::InvalidTransition);
+        }
+        let now = env.ledger().timestamp();
+        if now.abs_diff(booking.start_date) > CHECK_IN_WINDOW {
+            return Err(BookingError::CheckInWindowClosed);
         }
 
-        panic!("Booking not found");
+        let release =
+            booking.escrow_balance * booking.check_in_release_bps as i128 / BPS_DENOMINATOR as i128;
+        let payout = Self::disburse(&env, &mut booking, 0, release)?;
+
+        booking.status = BookingStatus::CheckedIn;
+        booking.checked_in_at = Some(now);
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(&env, EVT_CHECKED_IN, &booking, (now, payout));
+
+        Ok(booking)
     }
 
-    /// Update booking status
-    pub fn update_status(
+    /// Check out of a checked-in booking, guest only. Completes the booking and
+    /// releases the rest of the payment to the host.
+    pub fn check_out(env: Env, booking_id: u64) -> Result<Booking, BookingError> {
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        booking.guest.require_auth();
+
+        Self::ensure_not_disputed(&booking)?;
+        if booking.status != BookingStatus::CheckedIn {
+            return Err(BookingError::InvalidTransition);
+        }
+
+        let payout = Self::release_escrow(&env, &mut booking)?;
+        booking.status = BookingStatus::Completed;
+        Self::save_booking(&env, &booking);
+
+        // Also published as a completion, like every other way a stay completes
+        Self::publish_event(
+            &env,
+            EVT_CHECKED_OUT,
+            &booking,
+            (env.ledger().timestamp(), payout),
+        );
+        Self::publish_event(
+            &env,
+            EVT_COMPLETED,
+            &booking,
+            (booking.guest.clone(), payout),
+        );
+
+        Ok(booking)
+    }
+
+    /// Complete a confirmed or checked-in booking nobody disputed within
+    /// `COMPLETION_GRACE` of check-out, releasing the payment to the host. Anyone
+    /// can call this.
+    pub fn complete_booking(env: Env, booking_id: u64) -> Result<Booking, BookingError> {
+        let mut booking = Self::load_booking(&env, booking_id)?;
+
+        Self::ensure_not_disputed(&booking)?;
+        if !matches!(
+            booking.status,
+            BookingStatus::Confirmed | BookingStatus::CheckedIn
+        ) {
+            return Err(BookingError::InvalidTransition);
+        }
+        if env.ledger().timestamp() < booking.end_date + COMPLETION_GRACE {
+            return Err(BookingError::GracePeriodActive);
+        }
+
+        let payout = Self::release_escrow(&env, &mut booking)?;
+        booking.status = BookingStatus::Completed;
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(
+            &env,
+            EVT_COMPLETED,
+            &booking,
+            (env.current_contract_address(), payout),
+        );
+
+        Ok(booking)
+    }
+
+    /// File a claim against a booking's security deposit, host only. Claims can be
+    /// filed from check-out until the end of the deposit's claim window.
+    pub fn claim_deposit(
+        env: Env,
+        booking_id: u64,
+        host: Address,
+        amount: i128,
+    ) -> Result<Booking, BookingError> {
+        host.require_auth();
+
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        if booking.host != host {
+            return Err(BookingError::Unauthorized);
+        }
+
+        // Only stays that went ahead can be claimed against
+        Self::ensure_not_disputed(&booking)?;
+        if !matches!(
+            booking.status,
+            BookingStatus::Confirmed | BookingStatus::CheckedIn | BookingStatus::Completed
+        ) {
+            return Err(BookingError::InvalidTransition);
+        }
+
+        let now = env.ledger().timestamp();
+        let end_date = booking.end_date;
+        let deposit = Self::deposit_internal(&mut booking)?;
+        if deposit.status != DepositStatus::Held {
+            return Err(BookingError::InvalidTransition);
+        }
+        if now < end_date || now >= end_date + deposit.claim_window {
+            return Err(BookingError::ClaimWindowClosed);
+        }
+        if amount <= 0 || amount > deposit.amount {
+            return Err(BookingError::InvalidClaim);
+        }
+
+        // The guest has another claim window to accept or dispute
+        deposit.claim = amount;
+        deposit.respond_by = now + deposit.claim_window;
+        deposit.status = DepositStatus::Claimed;
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(&env, EVT_DEPOSIT_CLAIMED, &booking, (host, amount));
+
+        Ok(booking)
+    }
+
+    /// Accept the host's claim on the security deposit, guest only. The claim is
+    /// paid to the host and the rest of the deposit refunded.
+    pub fn accept_claim(
+        env: Env,
+        booking_id: u64,
+        guest: Address,
+    ) -> Result<Booking, BookingError> {
+        guest.require_auth();
+
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        if booking.guest != guest {
+            return Err(BookingError::Unauthorized);
+        }
+
+        Self::ensure_not_disputed(&booking)?;
+        let claim = Self::claimed_deposit(&mut booking)?.claim;
+        Self::settle_deposit(&env, &mut booking, claim)?;
+        Self::save_booking(&env, &booking);
+
+        Ok(booking)
+    }
+
+    /// Dispute the host's claim on the security deposit, guest only. The deposit
+    /// stays in escrow until an arbitrator resolves the claim.
+    pub fn dispute_claim(
+        env: Env,
+        booking_id: u64,
+        guest: Address,
+    ) -> Result<Booking, BookingError> {
+        guest.require_auth();
+
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        if booking.guest != guest {
+            return Err(BookingError::Unauthorized);
+        }
+
+        Self::ensure_not_disputed(&booking)?;
+        let deposit = Self::claimed_deposit(&mut booking)?;
+        deposit.status = DepositStatus::Disputed;
+        let claim = deposit.claim;
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(&env, EVT_DEPOSIT_DISPUTED, &booking, (guest, claim));
+
+        Ok(booking)
+    }
+
+    /// Resolve a disputed deposit claim, arbitrators only: `award` (at most the
+    /// claim) goes to the host and the rest of the deposit to the guest
+    pub fn resolve_claim(
+        env: Env,
+        booking_id: u64,
+        arbitrator: Address,
+        award: i128,
+    ) -> Result<Booking, BookingError> {
+        arbitrator.require_auth();
+        Self::ensure_arbitrator(&env, &arbitrator)?;
+
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        let deposit = Self::deposit_internal(&mut booking)?;
+        if deposit.status != DepositStatus::Disputed {
+            return Err(BookingError::InvalidTransition);
+        }
+        if award < 0 || award > deposit.claim {
+            return Err(BookingError::InvalidClaim);
+        }
+
+        Self::settle_deposit(&env, &mut booking, award)?;
+        Self::save_booking(&env, &booking);
+
+        Ok(booking)
+    }
+
+    /// Release a security deposit once nothing more can happen to it: in full to
+    /// the guest after an unclaimed claim window, or split as claimed once the
+    /// guest's time to respond has passed. Anyone can call this.
+    pub fn release_deposit(env: Env, booking_id: u64) -> Result<Booking, BookingError> {
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        Self::ensure_not_disputed(&booking)?;
+        let end_date = booking.end_date;
+        let deposit = Self::deposit_internal(&mut booking)?;
+
+        let now = env.ledger().timestamp();
+        let to_host = match deposit.status {
+            DepositStatus::Held if now >= end_date + deposit.claim_window => 0,
+            DepositStatus::Claimed if now >= deposit.respond_by => deposit.claim,
+            DepositStatus::Held | DepositStatus::Claimed => {
+                return Err(BookingError::ClaimWindowOpen)
+            }
+            DepositStatus::Disputed | DepositStatus::Settled => {
+                return Err(BookingError::InvalidTransition)
+            }
+        };
+
+        Self::settle_deposit(&env, &mut booking, to_host)?;
+        Self::save_booking(&env, &booking);
+
+        Ok(booking)
+    }
+
+    /// Open a dispute over a confirmed or checked-in booking, guest or host only. The escrowed
+    /// payment and deposit are frozen until an arbitrator resolves it.
+    pub fn open_dispute(
         env: Env,
         booking_id: u64,
-        new_status: BookingStatus,
         caller: Address,
-    ) -> Booking {
-        // TODO: Add proper authorization check for host/system
+        evidence_hash: BytesN<32>,
+    ) -> Result<Booking, BookingError> {
         caller.require_auth();
 
-        let mut bookings_map: Vec<(u64, Booking)> = env
-            .storage()
-            .persistent()
-            .get(&BOOKINGS)
-            .unwrap_or(vec![&env]);
-
-        let mut booking_found = false;
-        let mut updated_booking = None;
-
-        for i in 0..bookings_map.len() {
-            let (id, mut booking) = bookings_map.get(i).unwrap();
-            if id == booking_id {
-                // Validate status transition
-                match (booking.status, new_status) {
-                    (BookingStatus::Pending, BookingStatus::Confirmed)
-                    | (BookingStatus::Confirmed, BookingStatus::Completed)
-                    | (BookingStatus::Pending, BookingStatus::Cancelled)
-                    | (BookingStatus::Confirmed, BookingStatus::Cancelled) => {
-                        booking.status = new_status;
-                        bookings_map.set(i, (id.clone(), booking.clone()));
-                        updated_booking = Some(booking.clone());
-                        booking_found = true;
-                    }
-                    _ => panic!("Invalid status transition"),
-                }
-                break;
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        if caller != booking.guest && caller != booking.host {
+            return Err(BookingError::Unauthorized);
+        }
+        Self::ensure_not_disputed(&booking)?;
+        if !matches!(
+            booking.status,
+            BookingStatus::Confirmed | BookingStatus::CheckedIn
+        ) {
+            return Err(BookingError::InvalidTransition);
+        }
+
+        let dispute = Dispute {
+            opened_by: caller.clone(),
+            evidence_hash: evidence_hash.clone(),
+            opened_at: env.ledger().timestamp(),
+            resolved_by: None,
+            guest_bps: 0,
+        };
+        Self::set_persistent(&env, &DataKey::Dispute(booking_id), &dispute);
+
+        booking.status = BookingStatus::Disputed;
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(&env, EVT_DISPUTED, &booking, (caller, evidence_hash));
+
+        Ok(booking)
+    }
+
+    /// Resolve a dispute, arbitrators only: `guest_bps` basis points of the escrowed
+    /// payment are refunded to the guest and the rest paid to the host, less fees.
+    /// The booking is cancelled on a full refund and completed otherwise.
+    pub fn resolve_dispute(
+        env: Env,
+        booking_id: u64,
+        arbitrator: Address,
+        guest_bps: u32,
+    ) -> Result<Booking, BookingError> {
+        arbitrator.require_auth();
+        Self::ensure_arbitrator(&env, &arbitrator)?;
+
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        if booking.status != BookingStatus::Disputed {
+            return Err(BookingError::InvalidTransition);
+        }
+        if guest_bps > BPS_DENOMINATOR {
+            return Err(BookingError::InvalidSplit);
+        }
+
+        let mut dispute = Self::get_dispute(env.clone(), booking_id)?;
+        dispute.resolved_by = Some(arbitrator.clone());
+        dispute.guest_bps = guest_bps;
+        Self::set_persistent(&env, &DataKey::Dispute(booking_id), &dispute);
+
+        let refund = booking.escrow_balance * guest_bps as i128 / BPS_DENOMINATOR as i128;
+        let payout = Self::settle_escrow(&env, &mut booking, refund)?;
+        if guest_bps == BPS_DENOMINATOR {
+            // A full refund cancels the booking, which returns any deposit too
+            booking.status = BookingStatus::Cancelled;
+            Self::settle_deposit(&env, &mut booking, 0)?;
+            Self::unindex_booking(&env, &booking);
+        } else {
+            booking.status = BookingStatus::Completed;
+        }
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(
+            &env,
+            EVT_RESOLVED,
+            &booking,
+            (arbitrator.clone(), guest_bps, refund, payout),
+        );
+        // A split completes the stay, so it is also published as a completion
+        if booking.status == BookingStatus::Completed {
+            Self::publish_event(&env, EVT_COMPLETED, &booking, (arbitrator, payout));
+        }
+
+        Ok(booking)
+    }
+
+    /// Get the dispute raised over a booking
+    pub fn get_dispute(env: Env, booking_id: u64) -> Result<Dispute, BookingError> {
+        Self::get_persistent(&env, &DataKey::Dispute(booking_id)).ok_or(BookingError::NotFound)
+    }
+
+    /// Get the current and upcoming bookings for a property. Cancelled, expired
+    /// and finished bookings drop out of the index but stay readable by ID.
+    pub fn get_property_bookings(env: Env, property_id: PropertyId) -> Vec<Booking> {
+        Self::extend_instance_ttl(&env);
+        let mut bookings = Vec::new(&env);
+        for booking_id in Self::property_booking_ids(&env, &property_id).iter() {
+            if let Ok(booking) = Self::load_booking(&env, booking_id) {
+                bookings.push_back(booking);
             }
         }
+        bookings
+    }
 
-        if !booking_found {
-            panic!("Booking not found");
+    /// Internal helper to check a stay against a property's booking rules, bookings
+    /// and calendar blocks
+    fn availability_internal(
+        env: &Env,
+        property_id: &PropertyId,
+        calendar: &PropertyCalendar,
+        check_in_day: u32,
+        check_out_day: u32,
+    ) -> Availability {
+        // Validate dates: at least one night, checking in after now
+        let now = env.ledger().timestamp();
+        let check_in_time = calendar.check_in_time(check_in_day);
+        if check_in_day >= check_out_day || check_in_time < now {
+            return Availability::InvalidDates;
         }
 
-        // Update storage
-        env.storage().persistent().set(&BOOKINGS, &bookings_map);
+        // The stay must satisfy the host's booking rules
+        let rules = Self::get_booking_rules(env.clone(), property_id.clone());
+        let availability = rules.check(check_in_day, check_out_day, check_in_time, now);
+        if availability != Availability::Available {
+            return availability;
+        }
 
-        // Update property bookings
-        if let Some(booking) = &updated_booking {
-            let mut prop_bookings: Vec<Booking> = env
-                .storage()
-                .persistent()
-                .get(&booking.property_id)
-                .unwrap_or(vec![&env]);
+        Self::calendar_availability(env, property_id, check_in_day, check_out_day)
+    }
 
-            for i in 0..prop_bookings.len() {
-                if prop_bookings.get(i).unwrap().id == booking_id {
-                    prop_bookings.set(i, booking.clone());
-                    break;
+    /// Internal helper to check nights against a property's bookings and calendar
+    /// blocks. Ranges exclude their check-out day, so back-to-back stays where one
+    /// checks out on the day the next checks in don't overlap.
+    fn calendar_availability(
+        env: &Env,
+        property_id: &PropertyId,
+        check_in_day: u32,
+        check_out_day: u32,
+    ) -> Availability {
+        // Check for overlaps against every booking for this property. Cancelled
+        // bookings and Pending bookings whose hold has expired free their nights.
+        let now = env.ledger().timestamp();
+        for booking_id in Self::property_booking_ids(env, property_id).iter() {
+            let booking = match Self::load_booking(env, booking_id) {
+                Ok(booking) => booking,
+                Err(_) => continue,
+            };
+            if booking.holds_dates(now)
+                && check_in_day < booking.check_out_day
+                && booking.check_in_day < check_out_day
+            {
+                return Availability::Booked;
+            }
+        }
+
+        // Nights blocked by the host are unavailable too
+        for block in Self::get_blocks(env.clone(), property_id.clone()).iter() {
+            if check_in_day < block.end && block.start < check_out_day {
+                return Availability::Blocked;
+            }
+        }
+
+        Availability::Available
+    }
+
+    /// Internal helper to add a booking to its property's index. Bookings that no
+    /// longer reserve nights, or whose stay is over, are dropped on the way so the
+    /// index only grows with current and upcoming stays.
+    fn index_booking(env: &Env, booking: &Booking) {
+        let now = env.ledger().timestamp();
+        let mut booking_ids = Vec::new(env);
+        for booking_id in Self::property_booking_ids(env, &booking.property_id).iter() {
+            if let Ok(indexed) = Self::load_booking(env, booking_id) {
+                if indexed.holds_dates(now) && indexed.end_date > now {
+                    booking_ids.push_back(booking_id);
                 }
             }
+        }
+        booking_ids.push_back(booking.id);
+        Self::set_persistent(
+            env,
+            &DataKey::PropertyBookings(booking.property_id.clone()),
+            &booking_ids,
+        );
+    }
+
+    /// Internal helper to drop a cancelled or expired booking from its property's
+    /// index, freeing its nights without loading it on later availability checks
+    fn unindex_booking(env: &Env, booking: &Booking) {
+        let mut booking_ids = Self::property_booking_ids(env, &booking.property_id);
+        if let Some(index) = booking_ids.first_index_of(booking.id) {
+            booking_ids.remove(index);
+            Self::set_persistent(
+                env,
+                &DataKey::PropertyBookings(booking.property_id.clone()),
+                &booking_ids,
+            );
+        }
+    }
+
+    /// Internal helper to get the booking IDs for a property
+    fn property_booking_ids(env: &Env, property_id: &PropertyId) -> Vec<u64> {
+        Self::get_persistent(env, &DataKey::PropertyBookings(property_id.clone()))
+            .unwrap_or(Vec::new(env))
+    }
+
+    /// Internal helper to load a booking by ID
+    fn load_booking(env: &Env, booking_id: u64) -> Result<Booking, BookingError> {
+        Self::get_persistent(env, &DataKey::Booking(booking_id)).ok_or(BookingError::NotFound)
+    }
 
+    /// Internal helper to store a booking under its own key
+    fn save_booking(env: &Env, booking: &Booking) {
+        Self::set_persistent(env, &DataKey::Booking(booking.id), booking);
+        Self::extend_instance_ttl(env);
+    }
+
+    /// Internal helper to read a persistent entry, bumping its TTL if present
+    fn get_persistent<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+        let value = env.storage().persistent().get(key);
+        if value.is_some() {
             env.storage()
                 .persistent()
-                .set(&booking.property_id, &prop_bookings);
+                .extend_ttl(key, LIFETIME_THRESHOLD, BUMP_AMOUNT);
+        }
+        value
+    }
+
+    /// Internal helper to write a persistent entry and bump its TTL
+    fn set_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+        env.storage().persistent().set(key, value);
+        env.storage()
+            .persistent()
+            .extend_ttl(key, LIFETIME_THRESHOLD, BUMP_AMOUNT);
+    }
+
+    /// Internal helper to keep the contract instance (config and counter) alive
+    fn extend_instance_ttl(env: &Env) {
+        env.storage()
+            .instance()
+            .extend_ttl(LIFETIME_THRESHOLD, BUMP_AMOUNT);
+    }
+
+    /// Attach a cancellation policy to a property, listing owner only
+    pub fn set_cancellation_policy(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        policy: CancellationPolicy,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+
+        // Bound the number of tiers and cap refunds at 100%
+        if policy.tiers.len() > MAX_POLICY_TIERS {
+            return Err(BookingError::InvalidPolicy);
+        }
+        for tier in policy.tiers.iter() {
+            if tier.refund_bps > BPS_DENOMINATOR {
+                return Err(BookingError::InvalidPolicy);
+            }
+        }
+
+        Self::set_persistent(&env, &DataKey::Policy(property_id), &policy);
+        Self::extend_instance_ttl(&env);
+
+        Ok(true)
+    }
+
+    /// Attach booking rules to a property, listing owner only
+    pub fn set_booking_rules(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        rules: BookingRules,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+        if !rules.is_valid() {
+            return Err(BookingError::InvalidRules);
         }
 
-        // TODO: Trigger escrow actions based on status change
+        Self::set_persistent(&env, &DataKey::Rules(property_id), &rules);
+        Self::extend_instance_ttl(&env);
 
-        updated_booking.unwrap()
+        Ok(true)
     }
 
-    /// Get all bookings for a specific property
-    pub fn get_property_bookings(env: Env, property_id: String) -> Vec<Booking> {
-        Self::get_property_bookings_internal(&env, property_id)
+    /// Get the booking rules for a property (unrestricted if none are set)
+    pub fn get_booking_rules(env: Env, property_id: PropertyId) -> BookingRules {
+        Self::get_persistent(&env, &DataKey::Rules(property_id))
+            .unwrap_or_else(BookingRules::unrestricted)
     }
 
-    /// Internal helper to get property bookings
-    fn get_property_bookings_internal(env: &Env, property_id: String) -> Vec<Booking> {
+    /// Set a property's UTC offset and check-in/check-out hours, listing owner
+    /// only. Existing bookings keep the times they were made with.
+    pub fn set_calendar(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        calendar: PropertyCalendar,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+        if !calendar.is_valid() {
+            return Err(BookingError::InvalidCalendar);
+        }
+
+        Self::set_persistent(&env, &DataKey::Calendar(property_id), &calendar);
+        Self::extend_instance_ttl(&env);
+
+        Ok(true)
+    }
+
+    /// Get the calendar settings for a property (UTC, check-in 15:00, check-out
+    /// 11:00 if none are set)
+    pub fn get_calendar(env: Env, property_id: PropertyId) -> PropertyCalendar {
+        Self::get_persistent(&env, &DataKey::Calendar(property_id))
+            .unwrap_or_else(PropertyCalendar::utc)
+    }
+
+    /// Set a property's nightly pricing, listing owner only
+    pub fn set_pricing(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        pricing: PropertyPricing,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+        if !pricing.is_valid() {
+            return Err(BookingError::InvalidPricing);
+        }
+
+        Self::set_persistent(&env, &DataKey::Pricing(property_id), &pricing);
+        Self::extend_instance_ttl(&env);
+
+        Ok(true)
+    }
+
+    /// Get the nightly pricing for a property, if its host has set one
+    pub fn get_pricing(env: Env, property_id: PropertyId) -> Option<PropertyPricing> {
+        Self::get_persistent(&env, &DataKey::Pricing(property_id))
+    }
+
+    /// Price a stay from night `check_in_day` to `check_out_day` for `guests`
+    /// guests, itemized by base, weekend and seasonal nights, extra guests and
+    /// cleaning fee
+    pub fn quote(
+        env: Env,
+        property_id: PropertyId,
+        check_in_day: u32,
+        check_out_day: u32,
+        guests: u32,
+    ) -> Result<Quote, BookingError> {
+        if check_in_day >= check_out_day {
+            return Err(BookingError::InvalidDates);
+        }
+        if check_out_day - check_in_day > MAX_QUOTE_NIGHTS {
+            return Err(BookingError::StayTooLong);
+        }
+
+        let pricing =
+            Self::get_pricing(env.clone(), property_id).ok_or(BookingError::PricingNotSet)?;
+        if guests == 0 || (pricing.max_guests != 0 && guests > pricing.max_guests) {
+            return Err(BookingError::InvalidGuests);
+        }
+
+        Ok(pricing.quote(check_in_day, check_out_day, guests))
+    }
+
+    /// Set the security deposit a property requires, listing owner only
+    pub fn set_deposit_policy(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        policy: DepositPolicy,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+        if !policy.is_valid() {
+            return Err(BookingError::InvalidDeposit);
+        }
+
+        Self::set_persistent(&env, &DataKey::Deposit(property_id), &policy);
+        Self::extend_instance_ttl(&env);
+
+        Ok(true)
+    }
+
+    /// Remove a property's security deposit, listing owner only
+    pub fn remove_deposit_policy(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+
         env.storage()
             .persistent()
-            .get(&property_id)
-            .unwrap_or(vec![env])
+            .remove(&DataKey::Deposit(property_id));
+        Self::extend_instance_ttl(&env);
+
+        Ok(true)
+    }
+
+    /// Get the security deposit a property requires, if any
+    pub fn get_deposit_policy(env: Env, property_id: PropertyId) -> Option<DepositPolicy> {
+        Self::get_persistent(&env, &DataKey::Deposit(property_id))
+    }
+
+    /// Set the share of the payment, in basis points, released to the host when the
+    /// guest checks in, listing owner only. Bookings keep the share set when they
+    /// were made.
+    pub fn set_check_in_release(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        release_bps: u32,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+        if release_bps > BPS_DENOMINATOR {
+            return Err(BookingError::InvalidPolicy);
+        }
+
+        Self::set_persistent(&env, &DataKey::CheckInRelease(property_id), &release_bps);
+        Self::extend_instance_ttl(&env);
+
+        Ok(true)
+    }
+
+    /// Get the share of the payment released at check-in for a property (none if
+    /// not set)
+    pub fn get_check_in_release(env: Env, property_id: PropertyId) -> u32 {
+        Self::get_persistent(&env, &DataKey::CheckInRelease(property_id)).unwrap_or(0)
+    }
+
+    /// Get the cancellation policy for a property (full refund if none is set)
+    pub fn get_cancellation_policy(env: Env, property_id: PropertyId) -> CancellationPolicy {
+        Self::get_persistent(&env, &DataKey::Policy(property_id))
+            .unwrap_or_else(|| CancellationPolicy::full_refund(&env))
+    }
+
+    /// Block out dates on a property's calendar, listing owner only. The dates must
+    /// not overlap existing bookings or blocks.
+    pub fn create_block(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        block: Block,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+
+        let calendar = Self::get_calendar(env.clone(), property_id.clone());
+        let today = calendar.day_of(env.ledger().timestamp());
+        if block.start >= block.end || block.end <= today {
+            return Err(BookingError::InvalidDates);
+        }
+        if Self::calendar_availability(&env, &property_id, block.start, block.end)
+            != Availability::Available
+        {
+            return Err(BookingError::Overlap);
+        }
+
+        // Drop blocks that have already ended so the list stays bounded
+        let mut blocks = Vec::new(&env);
+        for existing in Self::get_blocks(env.clone(), property_id.clone()).iter() {
+            if existing.end > today {
+                blocks.push_back(existing);
+            }
+        }
+        if blocks.len() >= MAX_BLOCKS {
+            return Err(BookingError::TooManyBlocks);
+        }
+        blocks.push_back(block.clone());
+
+        Self::set_persistent(&env, &DataKey::Blocks(property_id.clone()), &blocks);
+        Self::extend_instance_ttl(&env);
+
+        env.events().publish(
+            (EVT_BLOCKED, property_id),
+            (block.start, block.end, block.reason),
+        );
+
+        Ok(true)
+    }
+
+    /// Remove the calendar block covering exactly `start`..`end`, listing owner only
+    pub fn remove_block(
+        env: Env,
+        property_id: PropertyId,
+        host: Address,
+        start: u32,
+        end: u32,
+    ) -> Result<bool, BookingError> {
+        Self::ensure_listing_owner(&env, &property_id, &host)?;
+
+        let mut blocks = Self::get_blocks(env.clone(), property_id.clone());
+        let index = blocks
+            .iter()
+            .position(|block| block.start == start && block.end == end)
+            .ok_or(BookingError::BlockNotFound)?;
+        blocks.remove(index as u32);
+
+        Self::set_persistent(&env, &DataKey::Blocks(property_id.clone()), &blocks);
+        Self::extend_instance_ttl(&env);
+
+        env.events()
+            .publish((EVT_UNBLOCKED, property_id), (start, end));
+
+        Ok(true)
+    }
+
+    /// Get the calendar blocks for a property
+    pub fn get_blocks(env: Env, property_id: PropertyId) -> Vec<Block> {
+        Self::get_persistent(&env, &DataKey::Blocks(property_id)).unwrap_or(Vec::new(&env))
+    }
+
+    /// Internal helper to fetch a property's listing from the property listing contract
+    fn listing_internal(
+        env: &Env,
+        property_id: &PropertyId,
+    ) -> Result<PropertyListing, BookingError> {
+        let listing_contract = Self::config_internal(env)?.listing_contract;
+        match PropertyListingClient::new(env, &listing_contract).try_get_listing(property_id) {
+            Ok(Ok(listing)) => Ok(listing),
+            _ => Err(BookingError::PropertyNotFound),
+        }
+    }
+
+    /// Internal helper to check that `host` owns a property's listing and signed
+    /// the call
+    fn ensure_listing_owner(
+        env: &Env,
+        property_id: &PropertyId,
+        host: &Address,
+    ) -> Result<(), BookingError> {
+        host.require_auth();
+        if Self::listing_internal(env, property_id)?.owner != *host {
+            return Err(BookingError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// Internal helper implementing the status transition permission matrix:
+    /// the host confirms, the host or operator completes, the guest or host
+    /// cancels, and the admin may perform any transition
+    fn can_update_status(
+        env: &Env,
+        booking: &Booking,
+        caller: &Address,
+        new_status: BookingStatus,
+    ) -> Result<bool, BookingError> {
+        if *caller == Self::admin_internal(env)? {
+            return Ok(true);
+        }
+
+        Ok(match new_status {
+            BookingStatus::Confirmed => *caller == booking.host,
+            BookingStatus::Completed => {
+                *caller == booking.host || Self::get_operator(env.clone()).as_ref() == Some(caller)
+            }
+            BookingStatus::Cancelled => *caller == booking.host || *caller == booking.guest,
+            BookingStatus::Pending
+            | BookingStatus::Expired
+            | BookingStatus::Disputed
+            | BookingStatus::CheckedIn => false,
+        })
+    }
+
+    /// Internal helper to get the admin address
+    fn admin_internal(env: &Env) -> Result<Address, BookingError> {
+        Ok(Self::config_internal(env)?.admin)
     }
 
-    /// Set escrow ID for a booking (called by escrow system)
+    /// Internal helper to get the payment token address
+    fn token_internal(env: &Env) -> Result<Address, BookingError> {
+        Ok(Self::config_internal(env)?.token)
+    }
+
+    /// Internal helper to get the contract configuration
+    fn config_internal(env: &Env) -> Result<Config, BookingError> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Config)
+            .ok_or(BookingError::NotInitialized)
+    }
+
+    /// Internal helper to reject fees above 100%
+    fn validate_config(config: &Config) -> Result<(), BookingError> {
+        if config.fee_bps > BPS_DENOMINATOR || config.guest_fee_bps > BPS_DENOMINATOR {
+            return Err(BookingError::InvalidConfig);
+        }
+        Ok(())
+    }
+
+    /// Internal helper to build a client for the payment token
+    fn token_client(env: &Env) -> Result<token::Client<'_>, BookingError> {
+        Ok(token::Client::new(env, &Self::token_internal(env)?))
+    }
+
+    /// Internal helper to pay out the escrowed balance of a booking to its host,
+    /// less platform fees, returning the amount the host received
+    fn release_escrow(env: &Env, booking: &mut Booking) -> Result<i128, BookingError> {
+        Self::settle_escrow(env, booking, 0)
+    }
+
+    /// Internal helper to settle the escrowed balance of a booking: `refund` goes
+    /// back to the guest, platform fees on the rest go to the treasury and the
+    /// remainder to the host, which is returned
+    fn settle_escrow(env: &Env, booking: &mut Booking, refund: i128) -> Result<i128, BookingError> {
+        let kept = booking.escrow_balance - refund;
+        Self::disburse(env, booking, refund, kept)
+    }
+
+    /// Internal helper to pay part of the escrowed balance of a booking: `refund`
+    /// back to the guest and `kept` to the host, less platform fees sent to the
+    /// treasury. Returns the amount the host received.
+    fn disburse(
+        env: &Env,
+        booking: &mut Booking,
+        refund: i128,
+        kept: i128,
+    ) -> Result<i128, BookingError> {
+        if booking.escrow_balance <= 0 || refund + kept <= 0 {
+            return Ok(0);
+        }
+
+        let (payout, fee) = booking.fee_split(kept);
+        let token = Self::token_client(env)?;
+        let contract = env.current_contract_address();
+
+        if refund > 0 {
+            token.transfer(&contract, &booking.guest, &refund);
+        }
+        if payout > 0 {
+            token.transfer(&contract, &booking.host, &payout);
+        }
+        if fee > 0 {
+            let treasury = Self::config_internal(env)?.treasury;
+            token.transfer(&contract, &treasury, &fee);
+            Self::publish_event(env, EVT_FEE_PAID, booking, (treasury, fee));
+        }
+        booking.escrow_balance -= refund + kept;
+
+        let mut settlement = Self::settlement_internal(env, booking);
+        settlement.guest_refund += refund;
+        settlement.host_payout += payout;
+        settlement.platform_fee += fee;
+        Self::set_persistent(env, &DataKey::Settlement(booking.id), &settlement);
+
+        Ok(payout)
+    }
+
+    /// Internal helper to get the settlement record of a booking, with the amount
+    /// still held taken from the booking
+    fn settlement_internal(env: &Env, booking: &Booking) -> Settlement {
+        let mut settlement =
+            Self::get_persistent(env, &DataKey::Settlement(booking.id)).unwrap_or(Settlement {
+                total_price: booking.total_price,
+                guest_fee: booking.guest_fee,
+                fee_bps: booking.fee_bps,
+                guest_refund: 0,
+                host_payout: 0,
+                platform_fee: 0,
+                held: 0,
+            });
+        settlement.held = booking.escrow_balance;
+        settlement
+    }
+
+    /// Internal helper to compute how much of the escrowed balance is refunded when the
+    /// guest cancels now, according to the booking's cancellation policy snapshot
+    fn cancellation_refund(env: &Env, booking: &Booking) -> i128 {
+        let refund_bps = booking
+            .cancellation_policy
+            .refund_bps(booking.start_date, env.ledger().timestamp());
+        booking.escrow_balance * refund_bps as i128 / BPS_DENOMINATOR as i128
+    }
+
+    /// Internal helper to reject changes to a booking's escrow while it is disputed
+    fn ensure_not_disputed(booking: &Booking) -> Result<(), BookingError> {
+        if booking.status == BookingStatus::Disputed {
+            return Err(BookingError::BookingDisputed);
+        }
+        Ok(())
+    }
+
+    /// Internal helper to check that `caller` sits on the arbitrator panel, or is
+    /// the admin while no panel is set
+    fn ensure_arbitrator(env: &Env, caller: &Address) -> Result<(), BookingError> {
+        let arbitrators = Self::get_arbitrators(env.clone());
+        let allowed = if arbitrators.is_empty() {
+            *caller == Self::admin_internal(env)?
+        } else {
+            arbitrators.contains(caller)
+        };
+        if !allowed {
+            return Err(BookingError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// Internal helper to get a booking's security deposit, if it required one
+    fn deposit_internal(booking: &mut Booking) -> Result<&mut Deposit, BookingError> {
+        if booking.deposit.amount == 0 {
+            return Err(BookingError::NoDeposit);
+        }
+        Ok(&mut booking.deposit)
+    }
+
+    /// Internal helper to get a booking's deposit with a claim awaiting the guest
+    fn claimed_deposit(booking: &mut Booking) -> Result<&mut Deposit, BookingError> {
+        let deposit = Self::deposit_internal(booking)?;
+        if deposit.status != DepositStatus::Claimed {
+            return Err(BookingError::InvalidTransition);
+        }
+        Ok(deposit)
+    }
+
+    /// Internal helper to pay out a booking's security deposit: `to_host` goes to
+    /// the host and the rest back to the guest. Platform fees don't apply.
+    fn settle_deposit(env: &Env, booking: &mut Booking, to_host: i128) -> Result<(), BookingError> {
+        let deposit = &mut booking.deposit;
+        if deposit.status == DepositStatus::Settled {
+            return Ok(());
+        }
+
+        let to_guest = deposit.amount - to_host;
+        let token = Self::token_client(env)?;
+        let contract = env.current_contract_address();
+
+        if to_guest > 0 {
+            token.transfer(&contract, &booking.guest, &to_guest);
+        }
+        if to_host > 0 {
+            token.transfer(&contract, &booking.host, &to_host);
+        }
+        deposit.status = DepositStatus::Settled;
+
+        Self::publish_event(env, EVT_DEPOSIT_SETTLED, booking, (to_guest, to_host));
+
+        Ok(())
+    }
+
+    /// Internal helper to refund a cancelled booking: `refund` goes back to the guest
+    /// and the remainder of the escrowed balance is paid to the host, less fees. Any
+    /// security deposit is returned in full.
+    fn refund_escrow(env: &Env, booking: &mut Booking, refund: i128) -> Result<(), BookingError> {
+        Self::settle_deposit(env, booking, 0)?;
+
+        if booking.escrow_balance <= 0 {
+            return Ok(());
+        }
+
+        let payout = Self::settle_escrow(env, booking, refund)?;
+
+        Self::publish_event(env, EVT_REFUNDED, booking, (refund, payout));
+
+        Ok(())
+    }
+
+    /// Internal helper to publish a booking event with the standard topic layout
+    fn publish_event<D>(env: &Env, name: Symbol, booking: &Booking, data: D)
+    where
+        D: IntoVal<Env, Val>,
+    {
+        env.events().publish(
+            (
+                name,
+                booking.id,
+                booking.property_id.clone(),
+                booking.guest.clone(),
+            ),
+            data,
+        );
+    }
+
+    /// Set escrow ID for a booking (called by escrow system), admin or operator only
     pub fn set_escrow_id(
         env: Env,
         booking_id: u64,
         escrow_id: String,
-        _caller: Address, // TODO: Verify this is the escrow contract
-    ) -> bool {
-        let mut bookings_map: Vec<(u64, Booking)> = env
-            .storage()
-            .persistent()
-            .get(&BOOKINGS)
-            .unwrap_or(vec![&env]);
-
-        for i in 0..bookings_map.len() {
-            let (id, mut booking) = bookings_map.get(i).unwrap();
-            if id == booking_id {
-                booking.escrow_id = Some(escrow_id);
-                bookings_map.set(i, (id, booking));
-                env.storage().persistent().set(&BOOKINGS, &bookings_map);
-                return true;
-            }
+        caller: Address,
+    ) -> Result<bool, BookingError> {
+        caller.require_auth();
+
+        if caller != Self::admin_internal(&env)?
+            && Self::get_operator(env.clone()).as_ref() != Some(&caller)
+        {
+            return Err(BookingError::Unauthorized);
         }
 
-        panic!("Booking not found");
+        let mut booking = Self::load_booking(&env, booking_id)?;
+        booking.escrow_id = Some(escrow_id.clone());
+        Self::save_booking(&env, &booking);
+
+        Self::publish_event(&env, EVT_ESCROW_LINKED, &booking, (escrow_id,));
+
+        Ok(true)
     }
 }
 
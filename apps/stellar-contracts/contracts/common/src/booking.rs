@@ -0,0 +1,648 @@
+use soroban_sdk::{contractclient, contracterror, contracttype, Address, BytesN, Env, String, Vec};
+
+use crate::PropertyId;
+
+// Contract errors
+#[contracterror]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum BookingError {
+    NotFound = 1,
+    Overlap = 2,
+    InvalidDates = 3,
+    Unauthorized = 4,
+    InvalidTransition = 5,
+    InvalidPrice = 6,
+    InvalidPolicy = 7,
+    NotInitialized = 8,
+    AlreadyInitialized = 9,
+    InvalidConfig = 10,
+    PropertyNotFound = 11,
+    PropertyUnavailable = 12,
+    HoldActive = 13,
+    HoldExpired = 14,
+    BlockNotFound = 15,
+    TooManyBlocks = 16,
+    StayTooShort = 17,
+    StayTooLong = 18,
+    InsufficientNotice = 19,
+    BeyondHorizon = 20,
+    CheckInDayNotAllowed = 21,
+    InvalidRules = 22,
+    InvalidCalendar = 23,
+    PriceMismatch = 24,
+    PricingNotSet = 25,
+    InvalidPricing = 26,
+    InvalidGuests = 27,
+    InvalidDeposit = 28,
+    NoDeposit = 29,
+    ClaimWindowClosed = 30,
+    ClaimWindowOpen = 31,
+    InvalidClaim = 32,
+    BookingDisputed = 33,
+    InvalidSplit = 34,
+    CheckInWindowClosed = 35,
+    GracePeriodActive = 36,
+}
+
+// Booking status enum
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BookingStatus {
+    Pending,
+    Confirmed,
+    Completed,
+    Cancelled,
+    Expired,
+    Disputed,
+    CheckedIn,
+}
+
+// A single refund tier: cancelling at least `cutoff` seconds before the
+// booking's start date refunds `refund_bps` basis points of the escrow
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CancellationTier {
+    pub cutoff: u64,
+    pub refund_bps: u32,
+}
+
+// Cancellation policy attached to a property by its host
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CancellationPolicy {
+    pub tiers: Vec<CancellationTier>,
+}
+
+pub const HOUR: u64 = 3_600;
+pub const DAY: u64 = 86_400;
+
+// Guests can check in up to this long before or after their check-in time
+pub const CHECK_IN_WINDOW: u64 = 12 * HOUR;
+
+// Time after check-out during which a stay can still be disputed before it
+// completes automatically
+pub const COMPLETION_GRACE: u64 = DAY;
+pub const BPS_DENOMINATOR: u32 = 10_000;
+pub const MAX_POLICY_TIERS: u32 = 10;
+
+impl CancellationPolicy {
+    /// Full refund up to 24 hours before check-in
+    pub fn flexible(env: &Env) -> Self {
+        Self::from_tiers(env, &[(DAY, BPS_DENOMINATOR)])
+    }
+
+    /// Full refund up to 5 days before check-in, 50% after that
+    pub fn moderate(env: &Env) -> Self {
+        Self::from_tiers(env, &[(5 * DAY, BPS_DENOMINATOR), (0, 5_000)])
+    }
+
+    /// 50% refund up to 7 days before check-in, nothing after that
+    pub fn strict(env: &Env) -> Self {
+        Self::from_tiers(env, &[(7 * DAY, 5_000)])
+    }
+
+    /// Full refund at any time, used for properties without a policy
+    pub fn full_refund(env: &Env) -> Self {
+        Self::from_tiers(env, &[(0, BPS_DENOMINATOR)])
+    }
+
+    fn from_tiers(env: &Env, tiers: &[(u64, u32)]) -> Self {
+        let mut policy_tiers = Vec::new(env);
+        for (cutoff, refund_bps) in tiers {
+            policy_tiers.push_back(CancellationTier {
+                cutoff: *cutoff,
+                refund_bps: *refund_bps,
+            });
+        }
+        Self {
+            tiers: policy_tiers,
+        }
+    }
+
+    /// Refund in basis points for a cancellation at `now` of a booking starting at `start_date`.
+    /// The most generous tier whose cutoff has not passed applies; nothing is refunded once
+    /// the stay has started.
+    pub fn refund_bps(&self, start_date: u64, now: u64) -> u32 {
+        if now >= start_date {
+            return 0;
+        }
+        let notice = start_date - now;
+        let mut refund_bps = 0;
+        for tier in self.tiers.iter() {
+            if notice >= tier.cutoff && tier.refund_bps > refund_bps {
+                refund_bps = tier.refund_bps;
+            }
+        }
+        refund_bps
+    }
+}
+
+// Main booking data structure
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Booking {
+    pub id: u64, // Changed to u64 for simplicity
+    pub property_id: PropertyId,
+    pub guest: Address,
+    pub host: Address,
+    pub check_in_day: u32, // First night, in the property's local days since epoch
+    pub check_out_day: u32, // Day after the last night
+    pub guests: u32,
+    pub start_date: u64, // Check-in time
+    pub end_date: u64,   // Check-out time
+    pub total_price: i128,
+    pub guest_fee: i128, // Service fee paid by the guest on top of `total_price`
+    pub fee_bps: u32,    // Platform fee on the host payout, snapshot taken at creation
+    pub status: BookingStatus,
+    pub escrow_id: Option<String>,
+    pub escrow_balance: i128, // Funds currently held in contract custody
+    pub cancellation_policy: CancellationPolicy, // Snapshot taken at creation
+    pub expires_at: Option<u64>, // End of the hold on a Pending booking; cleared on confirmation
+    pub deposit: Deposit,     // Security deposit, escrowed apart from the payment
+    pub check_in_release_bps: u32, // Share of the payment released to the host at check-in
+    pub checked_in_at: Option<u64>,
+}
+
+impl Booking {
+    /// Whether this is a Pending booking whose hold has run out at `now`
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.status == BookingStatus::Pending && self.expires_at.is_some_and(|at| now >= at)
+    }
+
+    /// Split `amount` of the escrowed payment kept from the guest into the host
+    /// payout and the platform fee. The guest fee is taken in proportion to the
+    /// amount kept, and the platform fee is deducted from the rest.
+    pub fn fee_split(&self, amount: i128) -> (i128, i128) {
+        let guest_fee = amount * self.guest_fee / (self.total_price + self.guest_fee);
+        let price = amount - guest_fee;
+        let host_fee = price * self.fee_bps as i128 / BPS_DENOMINATOR as i128;
+        (price - host_fee, guest_fee + host_fee)
+    }
+
+    /// Whether this booking still reserves its dates at `now`
+    pub fn holds_dates(&self, now: u64) -> bool {
+        match self.status {
+            BookingStatus::Cancelled | BookingStatus::Expired => false,
+            _ => !self.is_expired(now),
+        }
+    }
+}
+
+// Refundable security deposit a property requires, paid with the booking. The
+// host can claim from it for `claim_window` seconds after check-out.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DepositPolicy {
+    pub amount: i128,
+    pub claim_window: u64,
+}
+
+// Longest claim window a property can set
+pub const MAX_CLAIM_WINDOW: u64 = 30 * DAY;
+
+impl DepositPolicy {
+    /// Whether the policy is usable: a positive amount and a claim window of at
+    /// most `MAX_CLAIM_WINDOW`
+    pub fn is_valid(&self) -> bool {
+        self.amount > 0 && self.claim_window > 0 && self.claim_window <= MAX_CLAIM_WINDOW
+    }
+}
+
+// State of a booking's security deposit
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DepositStatus {
+    Held,     // In escrow, no claim filed
+    Claimed,  // The host claimed part or all of it, awaiting the guest
+    Disputed, // The guest disputed the claim, awaiting resolution
+    Settled,  // Paid out
+}
+
+// Security deposit held for a booking; a zero amount means none was required.
+// `claim` and `respond_by` are set when the host files a claim; an unanswered
+// claim is settled as accepted after `respond_by`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Deposit {
+    pub amount: i128,
+    pub claim_window: u64,
+    pub claim: i128,
+    pub respond_by: u64,
+    pub status: DepositStatus,
+}
+
+impl Deposit {
+    /// No deposit, for properties that don't require one
+    pub fn none() -> Self {
+        Self {
+            amount: 0,
+            claim_window: 0,
+            claim: 0,
+            respond_by: 0,
+            status: DepositStatus::Settled,
+        }
+    }
+
+    /// A deposit taken under `policy`, held until the claim window closes
+    pub fn held(policy: &DepositPolicy) -> Self {
+        Self {
+            amount: policy.amount,
+            claim_window: policy.claim_window,
+            claim: 0,
+            respond_by: 0,
+            status: DepositStatus::Held,
+        }
+    }
+}
+
+// Dispute raised by the guest or host over a booking, frozen in escrow until an
+// arbitrator resolves it. `evidence_hash` points to the off-chain evidence;
+// `resolved_by` and `guest_bps` are set on resolution.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dispute {
+    pub opened_by: Address,
+    pub evidence_hash: BytesN<32>,
+    pub opened_at: u64,
+    pub resolved_by: Option<Address>,
+    pub guest_bps: u32, // Share of the escrowed payment refunded to the guest
+}
+
+// Where a booking's payment has gone: refunded to the guest, paid out to the
+// host and taken as platform fees by the treasury. `held` is still in escrow.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Settlement {
+    pub total_price: i128,
+    pub guest_fee: i128,
+    pub fee_bps: u32,
+    pub guest_refund: i128,
+    pub host_payout: i128,
+    pub platform_fee: i128,
+    pub held: i128,
+}
+
+// Calendar settings attached to a property by its host. Nights are numbered by
+// days since the Unix epoch in the property's local time, `utc_offset` seconds
+// ahead of UTC. Guests check in at `check_in_hour` on their first night and check
+// out at `check_out_hour` on the day after their last, so a stay checking out on
+// the day another checks in never overlaps it.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyCalendar {
+    pub utc_offset: i32,
+    pub check_in_hour: u32,
+    pub check_out_hour: u32,
+}
+
+// Largest UTC offset in use (UTC+14 / UTC-14)
+pub const MAX_UTC_OFFSET: i32 = 14 * HOUR as i32;
+
+impl PropertyCalendar {
+    /// UTC, check-in at 15:00 and check-out at 11:00, used for properties
+    /// without calendar settings
+    pub fn utc() -> Self {
+        Self {
+            utc_offset: 0,
+            check_in_hour: 15,
+            check_out_hour: 11,
+        }
+    }
+
+    /// Whether the settings are consistent: a real UTC offset, and check-out no
+    /// later than check-in so back-to-back stays can share a day
+    pub fn is_valid(&self) -> bool {
+        self.utc_offset.abs() <= MAX_UTC_OFFSET
+            && self.check_in_hour < 24
+            && self.check_out_hour <= self.check_in_hour
+    }
+
+    /// The local day a UTC timestamp falls on
+    pub fn day_of(&self, timestamp: u64) -> u32 {
+        ((timestamp as i64 + self.utc_offset as i64).max(0) as u64 / DAY) as u32
+    }
+
+    /// Check-in time, as a UTC timestamp, for a stay whose first night is `day`
+    pub fn check_in_time(&self, day: u32) -> u64 {
+        self.day_start(day) + self.check_in_hour as u64 * HOUR
+    }
+
+    /// Check-out time, as a UTC timestamp, for a stay checking out on `day`
+    pub fn check_out_time(&self, day: u32) -> u64 {
+        self.day_start(day) + self.check_out_hour as u64 * HOUR
+    }
+
+    fn day_start(&self, day: u32) -> u64 {
+        (day as i64 * DAY as i64 - self.utc_offset as i64).max(0) as u64
+    }
+}
+
+// Nights a host has made unavailable on their property's calendar, from the
+// `start` night up to (not including) `end`, in the property's local days
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Block {
+    pub start: u32,
+    pub end: u32,
+    pub reason: String,
+}
+
+// Maximum number of upcoming calendar blocks a property can have
+pub const MAX_BLOCKS: u32 = 50;
+
+// Nightly rate overriding the base and weekend rates from the `start` night up
+// to (not including) `end`, in the property's local days
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SeasonalRate {
+    pub start: u32,
+    pub end: u32,
+    pub nightly_rate: i128,
+}
+
+// Nightly pricing attached to a property by its host, in the payment token.
+// Friday and Saturday nights are charged `weekend_rate`; each guest beyond
+// `included_guests` adds `extra_guest_fee` per night. A zero `max_guests` means
+// no limit.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyPricing {
+    pub base_rate: i128,
+    pub weekend_rate: i128,
+    pub seasons: Vec<SeasonalRate>,
+    pub cleaning_fee: i128,
+    pub included_guests: u32,
+    pub extra_guest_fee: i128,
+    pub max_guests: u32,
+}
+
+// Maximum number of seasonal rates per property, and of nights in a single quote
+pub const MAX_SEASONS: u32 = 20;
+pub const MAX_QUOTE_NIGHTS: u32 = 365;
+
+impl PropertyPricing {
+    /// Whether the pricing is usable: positive nightly rates, no negative fees
+    /// and well-formed seasons
+    pub fn is_valid(&self) -> bool {
+        if self.base_rate <= 0
+            || self.weekend_rate <= 0
+            || self.cleaning_fee < 0
+            || self.extra_guest_fee < 0
+            || self.seasons.len() > MAX_SEASONS
+        {
+            return false;
+        }
+        self.seasons
+            .iter()
+            .all(|season| season.start < season.end && season.nightly_rate > 0)
+    }
+
+    /// Rate for a single night; the first matching season wins over weekend and
+    /// base rates
+    pub fn nightly_rate(&self, night: u32) -> (i128, RateKind) {
+        for season in self.seasons.iter() {
+            if season.start <= night && night < season.end {
+                return (season.nightly_rate, RateKind::Seasonal);
+            }
+        }
+        match weekday(night) {
+            5 | 6 => (self.weekend_rate, RateKind::Weekend),
+            _ => (self.base_rate, RateKind::Base),
+        }
+    }
+
+    /// Price a stay from night `check_in_day` to `check_out_day` for `guests` guests
+    pub fn quote(&self, check_in_day: u32, check_out_day: u32, guests: u32) -> Quote {
+        let mut quote = Quote {
+            nights: check_out_day - check_in_day,
+            base_nights: 0,
+            base_total: 0,
+            weekend_nights: 0,
+            weekend_total: 0,
+            seasonal_nights: 0,
+            seasonal_total: 0,
+            extra_guest_total: 0,
+            cleaning_fee: self.cleaning_fee,
+            total: 0,
+        };
+        for night in check_in_day..check_out_day {
+            let (rate, kind) = self.nightly_rate(night);
+            match kind {
+                RateKind::Base => {
+                    quote.base_nights += 1;
+                    quote.base_total += rate;
+                }
+                RateKind::Weekend => {
+                    quote.weekend_nights += 1;
+                    quote.weekend_total += rate;
+                }
+                RateKind::Seasonal => {
+                    quote.seasonal_nights += 1;
+                    quote.seasonal_total += rate;
+                }
+            }
+        }
+        let extra_guests = guests.saturating_sub(self.included_guests);
+        quote.extra_guest_total =
+            self.extra_guest_fee * extra_guests as i128 * quote.nights as i128;
+        quote.total = quote.base_total
+            + quote.weekend_total
+            + quote.seasonal_total
+            + quote.extra_guest_total
+            + quote.cleaning_fee;
+        quote
+    }
+}
+
+// Which rate a night is charged at
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RateKind {
+    Base,
+    Weekend,
+    Seasonal,
+}
+
+// Line-itemized price of a stay, as computed by `quote`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Quote {
+    pub nights: u32,
+    pub base_nights: u32,
+    pub base_total: i128,
+    pub weekend_nights: u32,
+    pub weekend_total: i128,
+    pub seasonal_nights: u32,
+    pub seasonal_total: i128,
+    pub extra_guest_total: i128,
+    pub cleaning_fee: i128,
+    pub total: i128,
+}
+
+// Booking rules attached to a property by its host. Zero `min_nights`,
+// `max_nights`, `advance_notice` and `max_horizon` mean no limit;
+// `check_in_days` is a bitmask of allowed check-in weekdays (bit 0 = Sunday).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BookingRules {
+    pub min_nights: u32,
+    pub max_nights: u32,
+    pub advance_notice: u64, // Seconds between booking and check-in
+    pub max_horizon: u64,    // Seconds ahead check-in can be booked
+    pub check_in_days: u32,
+}
+
+// `check_in_days` mask allowing check-in on any day of the week
+pub const ALL_WEEKDAYS: u32 = 0b111_1111;
+
+impl BookingRules {
+    /// No restrictions, used for properties without rules
+    pub fn unrestricted() -> Self {
+        Self {
+            min_nights: 0,
+            max_nights: 0,
+            advance_notice: 0,
+            max_horizon: 0,
+            check_in_days: ALL_WEEKDAYS,
+        }
+    }
+
+    /// Whether the rules are consistent: a non-empty set of check-in weekdays and
+    /// a maximum stay no shorter than the minimum
+    pub fn is_valid(&self) -> bool {
+        self.check_in_days != 0
+            && self.check_in_days & !ALL_WEEKDAYS == 0
+            && (self.max_nights == 0 || self.max_nights >= self.min_nights)
+    }
+
+    /// Check a stay from night `check_in_day` to `check_out_day`, checking in at
+    /// `check_in_time` and booked at `now`, against the rules
+    pub fn check(
+        &self,
+        check_in_day: u32,
+        check_out_day: u32,
+        check_in_time: u64,
+        now: u64,
+    ) -> Availability {
+        let nights = check_out_day - check_in_day;
+        if nights < self.min_nights {
+            return Availability::StayTooShort;
+        }
+        if self.max_nights != 0 && nights > self.max_nights {
+            return Availability::StayTooLong;
+        }
+        if check_in_time < now.saturating_add(self.advance_notice) {
+            return Availability::InsufficientNotice;
+        }
+        if self.max_horizon != 0 && check_in_time > now.saturating_add(self.max_horizon) {
+            return Availability::BeyondHorizon;
+        }
+        if self.check_in_days & (1 << weekday(check_in_day)) == 0 {
+            return Availability::CheckInDayNotAllowed;
+        }
+        Availability::Available
+    }
+}
+
+/// Day of the week of a day since epoch, 0 = Sunday (the Unix epoch was a Thursday)
+pub fn weekday(day: u32) -> u32 {
+    (day + 4) % 7
+}
+
+// Result of an availability check: whether the dates can be booked, and if not why
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Availability {
+    Available,
+    InvalidDates,
+    Booked,
+    Blocked,
+    StayTooShort,
+    StayTooLong,
+    InsufficientNotice,
+    BeyondHorizon,
+    CheckInDayNotAllowed,
+}
+
+impl Availability {
+    /// The error `create_booking` fails with for these dates, if any
+    pub fn to_error(self) -> Option<BookingError> {
+        match self {
+            Availability::Available => None,
+            Availability::InvalidDates => Some(BookingError::InvalidDates),
+            Availability::Booked | Availability::Blocked => Some(BookingError::Overlap),
+            Availability::StayTooShort => Some(BookingError::StayTooShort),
+            Availability::StayTooLong => Some(BookingError::StayTooLong),
+            Availability::InsufficientNotice => Some(BookingError::InsufficientNotice),
+            Availability::BeyondHorizon => Some(BookingError::BeyondHorizon),
+            Availability::CheckInDayNotAllowed => Some(BookingError::CheckInDayNotAllowed),
+        }
+    }
+}
+
+// Contract configuration, set once by `initialize` and updated by the admin
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Config {
+    pub admin: Address,
+    pub token: Address, // SEP-41 token used for payments; fixed once set
+    pub listing_contract: Address, // PropertyListingContract holding the listings
+    pub fee_bps: u32,   // Platform fee deducted from host payouts, in basis points
+    pub guest_fee_bps: u32, // Service fee charged to guests on top of the price
+    pub treasury: Address, // Receives platform fees
+}
+
+// Storage keys. Contract configuration and the booking counter live in instance
+// storage; each booking and per-property index is its own persistent entry so
+// lookups stay O(1).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataKey {
+    Config,
+    Operator,
+    HoldPeriod,
+    BookingCount,
+    Booking(u64),
+    PropertyBookings(PropertyId), // Vec<u64> of booking IDs for a property
+    Policy(PropertyId),
+    Blocks(PropertyId), // Vec<Block> of calendar blocks for a property
+    Rules(PropertyId),
+    Calendar(PropertyId),
+    Pricing(PropertyId),
+    Settlement(u64),
+    Deposit(PropertyId),
+    Arbitrators,
+    Dispute(u64),
+    CheckInRelease(PropertyId), // u32 share of the payment released at check-in
+}
+
+// Read-only interface of the booking contract, for cross-contract calls
+#[contractclient(name = "BookingClient")]
+pub trait BookingInterface {
+    fn get_config(env: Env) -> Result<Config, BookingError>;
+    fn get_booking(env: Env, booking_id: u64) -> Result<Booking, BookingError>;
+    fn get_property_bookings(env: Env, property_id: PropertyId) -> Vec<Booking>;
+    fn check_availability(
+        env: Env,
+        property_id: PropertyId,
+        check_in_day: u32,
+        check_out_day: u32,
+    ) -> Availability;
+    fn get_cancellation_policy(env: Env, property_id: PropertyId) -> CancellationPolicy;
+    fn get_blocks(env: Env, property_id: PropertyId) -> Vec<Block>;
+    fn get_booking_rules(env: Env, property_id: PropertyId) -> BookingRules;
+    fn get_calendar(env: Env, property_id: PropertyId) -> PropertyCalendar;
+    fn get_settlement(env: Env, booking_id: u64) -> Result<Settlement, BookingError>;
+    fn get_pricing(env: Env, property_id: PropertyId) -> Option<PropertyPricing>;
+    fn get_deposit_policy(env: Env, property_id: PropertyId) -> Option<DepositPolicy>;
+    fn get_arbitrators(env: Env) -> Vec<Address>;
+    fn get_check_in_release(env: Env, property_id: PropertyId) -> u32;
+    fn get_dispute(env: Env, booking_id: u64) -> Result<Dispute, BookingError>;
+    fn quote(
+        env: Env,
+        property_id: PropertyId,
+        check_in_day: u32,
+        check_out_day: u32,
+        guests: u32,
+    ) -> Result<Quote, BookingError>;
+}
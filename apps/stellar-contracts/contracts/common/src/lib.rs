@@ -0,0 +1,21 @@
+#![no_std]
+
+// Types shared by the StellarRent contracts and by anything that talks to them
+// (tests, off-chain tooling). Each contract keeps its own storage, errors and
+// interface in its own module; `PropertyId` is the one type both contracts key on.
+
+use soroban_sdk::{contracttype, BytesN};
+
+pub mod booking;
+pub mod listing;
+pub mod storage;
+
+// Property identifier shared by the booking and property listing contracts: the
+// SHA-256 of the property's off-chain ID (e.g. its Supabase UUID), so IDs of any
+// format fit a fixed-size key
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyId(pub BytesN<32>);
+
+#[cfg(test)]
+mod test;
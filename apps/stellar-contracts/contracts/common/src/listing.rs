@@ -0,0 +1,72 @@
+use soroban_sdk::{contractclient, contracterror, contracttype, Address, Env, Symbol, Vec};
+
+use crate::PropertyId;
+
+#[contracterror]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum ListingError {
+    AlreadyExists = 1,
+    NotFound = 2,
+    NotOwner = 3,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PropertyStatus {
+    Available,
+    Booked,
+    Maintenance,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PropertyListing {
+    pub id: PropertyId,
+    pub data_hash: Symbol,
+    pub owner: Address,
+    pub status: PropertyStatus,
+}
+
+// Storage keys. The listing count lives in instance storage; listings and the
+// listing index live in persistent storage so they don't grow the instance entry.
+// Listing IDs are kept in a dense, position-addressed index so listings can be
+// enumerated page by page.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataKey {
+    Count,
+    Listing(PropertyId),
+    IndexAt(u32),
+    IndexOf(PropertyId),
+}
+
+// Storage layout from before listings were keyed by `PropertyId`. Listings were
+// stored under `LegacyDataKey::Listing`, or under their raw Symbol ID in instance
+// storage before that; `migrate_listing` moves them to the current layout.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LegacyDataKey {
+    Listing(Symbol),
+    IndexOf(Symbol),
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyListing {
+    pub id: Symbol,
+    pub data_hash: Symbol,
+    pub owner: Address,
+    pub status: PropertyStatus,
+}
+
+// Maximum number of listings returned by a single `get_listings` call
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+// Read-only interface of the property listing contract, for cross-contract calls
+#[contractclient(name = "PropertyListingClient")]
+pub trait PropertyListingInterface {
+    fn get_listing(env: Env, id: PropertyId) -> Result<PropertyListing, ListingError>;
+    fn get_listings(env: Env, offset: u32, limit: u32) -> Vec<PropertyListing>;
+    fn get_listing_count(env: Env) -> u32;
+}
@@ -0,0 +1,6 @@
+// TTL management, in ledgers (~5s each). Both contracts bump persistent entries
+// and their contract instance back to `BUMP_AMOUNT` whenever they are touched and
+// their TTL has fallen below `LIFETIME_THRESHOLD`.
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+pub const BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub const LIFETIME_THRESHOLD: u32 = BUMP_AMOUNT - DAY_IN_LEDGERS;
@@ -0,0 +1,285 @@
+#![cfg(test)]
+
+use crate::booking::{
+    weekday, Availability, Booking, BookingRules, BookingStatus, CancellationPolicy, Deposit,
+    DepositPolicy, PropertyCalendar, PropertyPricing, Quote, SeasonalRate, DAY, HOUR,
+    MAX_CLAIM_WINDOW, MAX_SEASONS, MAX_UTC_OFFSET,
+};
+use crate::PropertyId;
+use soroban_sdk::{testutils::Address as _, vec, Address, BytesN, Env, Vec};
+
+#[test]
+fn test_cancellation_policy_refund_bps() {
+    let env = Env::default();
+    let start_date = 1704067200u64;
+
+    let flexible = CancellationPolicy::flexible(&env);
+    assert_eq!(
+        flexible.refund_bps(start_date, start_date - 2 * 86_400),
+        10_000
+    );
+    assert_eq!(flexible.refund_bps(start_date, start_date - 3_600), 0);
+
+    let moderate = CancellationPolicy::moderate(&env);
+    assert_eq!(
+        moderate.refund_bps(start_date, start_date - 5 * 86_400),
+        10_000
+    );
+    assert_eq!(moderate.refund_bps(start_date, start_date - 86_400), 5_000);
+    assert_eq!(moderate.refund_bps(start_date, start_date - 1), 5_000);
+    assert_eq!(moderate.refund_bps(start_date, start_date), 0);
+
+    let strict = CancellationPolicy::strict(&env);
+    assert_eq!(
+        strict.refund_bps(start_date, start_date - 10 * 86_400),
+        5_000
+    );
+    assert_eq!(strict.refund_bps(start_date, start_date - 6 * 86_400), 0);
+}
+
+#[test]
+fn test_weekday() {
+    assert_eq!(weekday(0), 4); // Thursday, Jan 1 1970
+    assert_eq!(weekday(19723), 1); // Monday, Jan 1 2024
+    assert_eq!(weekday(19722), 0);
+}
+
+#[test]
+fn test_property_calendar() {
+    let utc = PropertyCalendar::utc();
+    assert!(utc.is_valid());
+    assert_eq!(utc.day_of(1704067200), 19723); // Jan 1 2024, 00:00 UTC
+    assert_eq!(utc.day_of(1704067200 - 1), 19722);
+    assert_eq!(utc.check_in_time(19723), 1704067200 + 15 * HOUR);
+    assert_eq!(utc.check_out_time(19724), 1704153600 + 11 * HOUR);
+
+    // UTC-5: local midnight is 05:00 UTC
+    let new_york = PropertyCalendar {
+        utc_offset: -5 * HOUR as i32,
+        check_in_hour: 16,
+        check_out_hour: 10,
+    };
+    assert!(new_york.is_valid());
+    assert_eq!(new_york.day_of(1704067200 + 4 * HOUR), 19722);
+    assert_eq!(new_york.day_of(1704067200 + 5 * HOUR), 19723);
+    assert_eq!(new_york.check_in_time(19723), 1704067200 + 21 * HOUR);
+
+    // Checking out after check-in would make back-to-back stays overlap
+    let mut invalid = PropertyCalendar::utc();
+    invalid.check_out_hour = 16;
+    assert!(!invalid.is_valid());
+
+    invalid = PropertyCalendar::utc();
+    invalid.utc_offset = MAX_UTC_OFFSET + 1;
+    assert!(!invalid.is_valid());
+
+    invalid = PropertyCalendar::utc();
+    invalid.check_in_hour = 24;
+    assert!(!invalid.is_valid());
+}
+
+#[test]
+fn test_booking_rules_check() {
+    let now = 1703980800u64; // Sunday, Dec 31 2023
+    let monday = 19723u32;
+    let check_in = PropertyCalendar::utc().check_in_time(monday);
+    let rules = BookingRules {
+        min_nights: 2,
+        max_nights: 7,
+        advance_notice: DAY,
+        max_horizon: 30 * DAY,
+        check_in_days: 1 << 1 | 1 << 5, // Monday and Friday
+    };
+    assert!(rules.is_valid());
+
+    assert_eq!(
+        rules.check(monday, monday + 2, check_in, now),
+        Availability::Available
+    );
+    assert_eq!(
+        rules.check(monday, monday + 1, check_in, now),
+        Availability::StayTooShort
+    );
+    assert_eq!(
+        rules.check(monday, monday + 8, check_in, now),
+        Availability::StayTooLong
+    );
+    assert_eq!(
+        rules.check(monday, monday + 2, check_in, check_in - DAY + 1),
+        Availability::InsufficientNotice
+    );
+    assert_eq!(
+        rules.check(monday + 35, monday + 37, check_in + 35 * DAY, now),
+        Availability::BeyondHorizon
+    );
+    assert_eq!(
+        rules.check(monday + 1, monday + 3, check_in + DAY, now),
+        Availability::CheckInDayNotAllowed
+    );
+
+    let unrestricted = BookingRules::unrestricted();
+    assert!(unrestricted.is_valid());
+    assert_eq!(
+        unrestricted.check(monday, monday + 1, check_in, now),
+        Availability::Available
+    );
+}
+
+#[test]
+fn test_booking_rules_is_valid() {
+    let mut rules = BookingRules::unrestricted();
+    rules.check_in_days = 0;
+    assert!(!rules.is_valid());
+
+    rules.check_in_days = 1 << 7;
+    assert!(!rules.is_valid());
+
+    rules = BookingRules::unrestricted();
+    rules.min_nights = 3;
+    rules.max_nights = 2;
+    assert!(!rules.is_valid());
+}
+
+fn sample_pricing(env: &Env) -> PropertyPricing {
+    PropertyPricing {
+        base_rate: 100,
+        weekend_rate: 150,
+        // Sat Jan 6 to Mon Jan 8, 2024
+        seasons: vec![
+            env,
+            SeasonalRate {
+                start: 19728,
+                end: 19730,
+                nightly_rate: 200,
+            },
+        ],
+        cleaning_fee: 50,
+        included_guests: 2,
+        extra_guest_fee: 10,
+        max_guests: 4,
+    }
+}
+
+#[test]
+fn test_property_pricing_quote() {
+    let env = Env::default();
+    let pricing = sample_pricing(&env);
+
+    // Wed Jan 3 to Tue Jan 9: Wed, Thu and Mon at base, Fri at weekend, Sat and
+    // Sun in season
+    assert_eq!(
+        pricing.quote(19725, 19731, 3),
+        Quote {
+            nights: 6,
+            base_nights: 3,
+            base_total: 300,
+            weekend_nights: 1,
+            weekend_total: 150,
+            seasonal_nights: 2,
+            seasonal_total: 400,
+            extra_guest_total: 60,
+            cleaning_fee: 50,
+            total: 960,
+        }
+    );
+
+    // A single weeknight within the included guests
+    assert_eq!(pricing.quote(19723, 19724, 2).total, 150);
+}
+
+#[test]
+fn test_property_pricing_is_valid() {
+    let env = Env::default();
+    assert!(sample_pricing(&env).is_valid());
+
+    let mut pricing = sample_pricing(&env);
+    pricing.base_rate = 0;
+    assert!(!pricing.is_valid());
+
+    pricing = sample_pricing(&env);
+    pricing.cleaning_fee = -1;
+    assert!(!pricing.is_valid());
+
+    pricing = sample_pricing(&env);
+    pricing.seasons = vec![
+        &env,
+        SeasonalRate {
+            start: 19730,
+            end: 19730,
+            nightly_rate: 200,
+        },
+    ];
+    assert!(!pricing.is_valid());
+
+    pricing = sample_pricing(&env);
+    pricing.seasons = Vec::new(&env);
+    for i in 0..=MAX_SEASONS {
+        pricing.seasons.push_back(SeasonalRate {
+            start: i,
+            end: i + 1,
+            nightly_rate: 200,
+        });
+    }
+    assert!(!pricing.is_valid());
+}
+
+#[test]
+fn test_booking_fee_split() {
+    let env = Env::default();
+    let mut booking = Booking {
+        id: 0,
+        property_id: PropertyId(BytesN::from_array(&env, &[0; 32])),
+        guest: Address::generate(&env),
+        host: Address::generate(&env),
+        check_in_day: 19723,
+        check_out_day: 19724,
+        guests: 1,
+        start_date: 19723 * DAY + 15 * HOUR,
+        end_date: 19724 * DAY + 11 * HOUR,
+        total_price: 10_000,
+        guest_fee: 500,
+        fee_bps: 300,
+        status: BookingStatus::Confirmed,
+        escrow_id: None,
+        escrow_balance: 10_500,
+        cancellation_policy: CancellationPolicy::full_refund(&env),
+        expires_at: None,
+        deposit: Deposit::none(),
+        check_in_release_bps: 0,
+        checked_in_at: None,
+    };
+
+    // The whole payment: 3% of the price from the host, plus the guest fee
+    assert_eq!(booking.fee_split(10_500), (9_700, 800));
+    // Half of it, e.g. after a 50% refund
+    assert_eq!(booking.fee_split(5_250), (4_850, 400));
+    assert_eq!(booking.fee_split(0), (0, 0));
+
+    booking.guest_fee = 0;
+    booking.fee_bps = 0;
+    assert_eq!(booking.fee_split(10_000), (10_000, 0));
+}
+
+#[test]
+fn test_deposit_policy_is_valid() {
+    let policy = DepositPolicy {
+        amount: 2_000,
+        claim_window: 3 * DAY,
+    };
+    assert!(policy.is_valid());
+    assert!(!DepositPolicy {
+        amount: 0,
+        ..policy.clone()
+    }
+    .is_valid());
+    assert!(!DepositPolicy {
+        claim_window: 0,
+        ..policy.clone()
+    }
+    .is_valid());
+    assert!(!DepositPolicy {
+        claim_window: MAX_CLAIM_WINDOW + 1,
+        ..policy
+    }
+    .is_valid());
+}
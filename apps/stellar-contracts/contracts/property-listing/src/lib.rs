@@ -1,24 +1,11 @@
 #![no_std]
-use soroban_sdk::{
-    contract, contractimpl, contracttype, symbol_short, vec, Address, Env, Symbol, Vec,
+use common::listing::{
+    DataKey, LegacyDataKey, LegacyListing, ListingError, PropertyListing, PropertyStatus,
+    MAX_PAGE_SIZE,
 };
-
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum PropertyStatus {
-    Available,
-    Booked,
-    Maintenance,
-}
-
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct PropertyListing {
-    pub id: Symbol,
-    pub data_hash: Symbol,
-    pub owner: Address,
-    pub status: PropertyStatus,
-}
+use common::storage::{BUMP_AMOUNT, LIFETIME_THRESHOLD};
+use common::PropertyId;
+use soroban_sdk::{contract, contractimpl, Address, Env, IntoVal, Symbol, TryFromVal, Val, Vec};
 
 #[contract]
 pub struct PropertyListingContract;
@@ -37,13 +24,19 @@ impl PropertyListingContract {
     // Creates a new property listing
     pub fn create_listing(
         env: &Env,
-        id: Symbol,
+        id: PropertyId,
         data_hash: Symbol,
         owner: Address,
-    ) -> PropertyListing {
+    ) -> Result<PropertyListing, ListingError> {
+        owner.require_auth();
+
         // Check if listing already exists
-        if env.storage().instance().has(&id) {
-            panic!("Property listing already exists");
+        if env
+            .storage()
+            .persistent()
+            .has(&DataKey::Listing(id.clone()))
+        {
+            return Err(ListingError::AlreadyExists);
         }
 
         let listing = PropertyListing {
@@ -54,28 +47,87 @@ impl PropertyListingContract {
         };
 
         // Store the listing
-        env.storage().instance().set(&id, &listing);
+        Self::save_listing(env, &listing);
+
+        // Append it to the listing index
+        Self::append_to_index(env, &id);
+        Self::extend_instance_ttl(env);
+
+        Ok(listing)
+    }
+
+    // Moves a listing stored under its legacy Symbol ID to `id`, keeping its position
+    // in the listing index. Must be signed by the listing's owner.
+    pub fn migrate_listing(
+        env: &Env,
+        legacy_id: Symbol,
+        id: PropertyId,
+    ) -> Result<PropertyListing, ListingError> {
+        if env
+            .storage()
+            .persistent()
+            .has(&DataKey::Listing(id.clone()))
+        {
+            return Err(ListingError::AlreadyExists);
+        }
+
+        // Find the listing in either legacy location
+        let legacy_key = LegacyDataKey::Listing(legacy_id.clone());
+        let in_persistent = env.storage().persistent().has(&legacy_key);
+        let legacy: LegacyListing = if in_persistent {
+            env.storage().persistent().get(&legacy_key)
+        } else {
+            env.storage().instance().get(&legacy_id)
+        }
+        .ok_or(ListingError::NotFound)?;
+
+        legacy.owner.require_auth();
+
+        if in_persistent {
+            env.storage().persistent().remove(&legacy_key);
+        } else {
+            env.storage().instance().remove(&legacy_id);
+        }
+
+        let listing = PropertyListing {
+            id: id.clone(),
+            data_hash: legacy.data_hash,
+            owner: legacy.owner,
+            status: legacy.status,
+        };
+        Self::save_listing(env, &listing);
+
+        // Re-point the legacy index entry, or index listings that predate the index
+        let legacy_index_key = LegacyDataKey::IndexOf(legacy_id);
+        let position: Option<u32> = env.storage().persistent().get(&legacy_index_key);
+        match position {
+            Some(position) => {
+                env.storage().persistent().remove(&legacy_index_key);
+                Self::set_persistent(env, &DataKey::IndexAt(position), &id);
+                Self::set_persistent(env, &DataKey::IndexOf(id), &position);
+            }
+            None => Self::append_to_index(env, &id),
+        }
+        Self::extend_instance_ttl(env);
 
-        listing
+        Ok(listing)
     }
 
     // Updates an existing property listing
     pub fn update_listing(
         env: &Env,
-        id: Symbol,
+        id: PropertyId,
         data_hash: Symbol,
         owner: Address,
-    ) -> PropertyListing {
+    ) -> Result<PropertyListing, ListingError> {
+        owner.require_auth();
+
         // Get the existing listing
-        let listing: PropertyListing = env
-            .storage()
-            .instance()
-            .get(&id)
-            .unwrap_or_else(|| panic!("Property listing not found"));
+        let listing = Self::load_listing(env, &id)?;
 
         // Verify ownership
         if listing.owner != owner {
-            panic!("Only the owner can update the listing");
+            return Err(ListingError::NotOwner);
         }
 
         // Create updated listing
@@ -87,28 +139,27 @@ impl PropertyListingContract {
         };
 
         // Store the updated listing
-        env.storage().instance().set(&id, &updated_listing);
+        Self::save_listing(env, &updated_listing);
+        Self::extend_listing_entries(env, &id, LIFETIME_THRESHOLD)?;
 
-        updated_listing
+        Ok(updated_listing)
     }
 
     // Updates the status of a property listing
     pub fn update_status(
         env: &Env,
-        id: Symbol,
+        id: PropertyId,
         owner: Address,
         status: PropertyStatus,
-    ) -> PropertyListing {
+    ) -> Result<PropertyListing, ListingError> {
+        owner.require_auth();
+
         // Get the existing listing
-        let listing: PropertyListing = env
-            .storage()
-            .instance()
-            .get(&id)
-            .unwrap_or_else(|| panic!("Property listing not found"));
+        let listing = Self::load_listing(env, &id)?;
 
         // Verify ownership
         if listing.owner != owner {
-            panic!("Only the owner can update the status");
+            return Err(ListingError::NotOwner);
         }
 
         // Create updated listing
@@ -120,26 +171,168 @@ impl PropertyListingContract {
         };
 
         // Store the updated listing
-        env.storage().instance().set(&id, &updated_listing);
+        Self::save_listing(env, &updated_listing);
+        Self::extend_listing_entries(env, &id, LIFETIME_THRESHOLD)?;
 
-        updated_listing
+        Ok(updated_listing)
     }
 
     // Gets a property listing by ID
-    pub fn get_listing(env: &Env, id: Symbol) -> PropertyListing {
+    pub fn get_listing(env: &Env, id: PropertyId) -> Result<PropertyListing, ListingError> {
+        Self::load_listing(env, &id)
+    }
+
+    // Extends the TTL of a listing and its index entries so they don't get archived.
+    // Anyone can pay to keep a listing alive; no authorization is required.
+    pub fn extend_listing_ttl(env: &Env, id: PropertyId) -> Result<(), ListingError> {
+        if !env
+            .storage()
+            .persistent()
+            .has(&DataKey::Listing(id.clone()))
+        {
+            return Err(ListingError::NotFound);
+        }
+        Self::extend_listing_entries(env, &id, BUMP_AMOUNT)?;
+        Self::extend_instance_ttl(env);
+
+        Ok(())
+    }
+
+    // Removes a property listing from the registry
+    pub fn remove_listing(env: &Env, id: PropertyId, owner: Address) -> Result<(), ListingError> {
+        owner.require_auth();
+
+        // Get the existing listing
+        let listing = Self::load_listing(env, &id)?;
+
+        // Verify ownership
+        if listing.owner != owner {
+            return Err(ListingError::NotOwner);
+        }
+
+        // Move the last indexed listing into the removed slot
+        let position: u32 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::IndexOf(id.clone()))
+            .ok_or(ListingError::NotFound)?;
+        let last = Self::get_listing_count(env) - 1;
+        if position != last {
+            let last_id: PropertyId = env
+                .storage()
+                .persistent()
+                .get(&DataKey::IndexAt(last))
+                .ok_or(ListingError::NotFound)?;
+            Self::set_persistent(env, &DataKey::IndexAt(position), &last_id);
+            Self::set_persistent(env, &DataKey::IndexOf(last_id), &position);
+        }
+        env.storage().persistent().remove(&DataKey::IndexAt(last));
         env.storage()
-            .instance()
-            .get(&id)
-            .unwrap_or_else(|| panic!("Property listing not found"))
+            .persistent()
+            .remove(&DataKey::IndexOf(id.clone()));
+        env.storage().instance().set(&DataKey::Count, &last);
+        Self::extend_instance_ttl(env);
+
+        env.storage().persistent().remove(&DataKey::Listing(id));
+
+        Ok(())
     }
 
-    // Gets all property listings
-    pub fn get_all_listings(env: &Env) -> Vec<PropertyListing> {
-        let mut listings = vec![env];
-        // Note: In a real implementation, you would need to implement a way to iterate over all listings
-        // This is a simplified version that would need to be expanded based on your specific needs
+    // Gets a page of property listings, at most `MAX_PAGE_SIZE` per call
+    pub fn get_listings(env: &Env, offset: u32, limit: u32) -> Vec<PropertyListing> {
+        let mut listings = Vec::new(env);
+        let count = Self::get_listing_count(env);
+        let end = offset.saturating_add(limit.min(MAX_PAGE_SIZE)).min(count);
+
+        for position in offset..end {
+            // Index entries that haven't been migrated yet hold a legacy Symbol ID
+            let key = DataKey::IndexAt(position);
+            let value: Option<Val> = env.storage().persistent().get(&key);
+            let id = value.and_then(|value| PropertyId::try_from_val(env, &value).ok());
+            if id.is_some() {
+                env.storage()
+                    .persistent()
+                    .extend_ttl(&key, LIFETIME_THRESHOLD, BUMP_AMOUNT);
+            }
+            if let Some(listing) = id.and_then(|id| Self::load_listing(env, &id).ok()) {
+                listings.push_back(listing);
+            }
+        }
+
         listings
     }
+
+    // Gets the total number of property listings
+    pub fn get_listing_count(env: &Env) -> u32 {
+        env.storage().instance().get(&DataKey::Count).unwrap_or(0)
+    }
+
+    // Internal helper to read a listing, bumping its TTL
+    fn load_listing(env: &Env, id: &PropertyId) -> Result<PropertyListing, ListingError> {
+        let key = DataKey::Listing(id.clone());
+        let listing = env
+            .storage()
+            .persistent()
+            .get(&key)
+            .ok_or(ListingError::NotFound)?;
+        env.storage()
+            .persistent()
+            .extend_ttl(&key, LIFETIME_THRESHOLD, BUMP_AMOUNT);
+        Ok(listing)
+    }
+
+    // Internal helper to append a listing ID to the end of the listing index
+    fn append_to_index(env: &Env, id: &PropertyId) {
+        let count = Self::get_listing_count(env);
+        Self::set_persistent(env, &DataKey::IndexAt(count), id);
+        Self::set_persistent(env, &DataKey::IndexOf(id.clone()), &count);
+        env.storage().instance().set(&DataKey::Count, &(count + 1));
+    }
+
+    // Internal helper to write a listing, bumping its TTL
+    fn save_listing(env: &Env, listing: &PropertyListing) {
+        Self::set_persistent(env, &DataKey::Listing(listing.id.clone()), listing);
+    }
+
+    // Internal helper to write a persistent entry and bump its TTL
+    fn set_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+        env.storage().persistent().set(key, value);
+        env.storage()
+            .persistent()
+            .extend_ttl(key, LIFETIME_THRESHOLD, BUMP_AMOUNT);
+    }
+
+    // Internal helper to bump a listing and its index entries whose TTL is below `threshold`
+    fn extend_listing_entries(
+        env: &Env,
+        id: &PropertyId,
+        threshold: u32,
+    ) -> Result<(), ListingError> {
+        let position: u32 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::IndexOf(id.clone()))
+            .ok_or(ListingError::NotFound)?;
+
+        for key in [
+            DataKey::Listing(id.clone()),
+            DataKey::IndexOf(id.clone()),
+            DataKey::IndexAt(position),
+        ] {
+            env.storage()
+                .persistent()
+                .extend_ttl(&key, threshold, BUMP_AMOUNT);
+        }
+
+        Ok(())
+    }
+
+    // Internal helper to keep the contract instance (and the listing count) alive
+    fn extend_instance_ttl(env: &Env) {
+        env.storage()
+            .instance()
+            .extend_ttl(LIFETIME_THRESHOLD, BUMP_AMOUNT);
+    }
 }
 
 mod test;
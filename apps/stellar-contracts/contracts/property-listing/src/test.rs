@@ -1,17 +1,35 @@
 #![cfg(test)]
 
+extern crate std;
+
 use super::*;
-use soroban_sdk::{symbol_short, Address, Env, Symbol};
-use soroban_sdk::testutils::Address as _;
+use common::listing::{DataKey, LegacyDataKey, LegacyListing, PropertyStatus, MAX_PAGE_SIZE};
+use common::storage::{BUMP_AMOUNT, DAY_IN_LEDGERS};
+use common::PropertyId;
+use soroban_sdk::testutils::{
+    storage::Persistent as _, Address as _, AuthorizedFunction, AuthorizedInvocation, Ledger,
+    MockAuth, MockAuthInvoke,
+};
+use soroban_sdk::{symbol_short, Address, Bytes, Env, IntoVal, Symbol};
+
+// Derives a property ID the way off-chain callers do: the SHA-256 of the off-chain ID
+fn property_id(env: &Env, off_chain_id: &str) -> PropertyId {
+    PropertyId(
+        env.crypto()
+            .sha256(&Bytes::from_slice(env, off_chain_id.as_bytes()))
+            .into(),
+    )
+}
 
 #[test]
 fn test_create_listing() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
 
     let listing = client.create_listing(&id, &data_hash, &owner);
@@ -23,28 +41,30 @@ fn test_create_listing() {
 }
 
 #[test]
-#[should_panic(expected = "Property listing already exists")]
 fn test_create_duplicate_listing() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
 
     client.create_listing(&id, &data_hash, &owner);
-    client.create_listing(&id, &data_hash, &owner);
+    let result = client.try_create_listing(&id, &data_hash, &owner);
+    assert_eq!(result, Err(Ok(ListingError::AlreadyExists)));
 }
 
 #[test]
 fn test_update_listing() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
     let new_data_hash = symbol_short!("HASH2");
 
@@ -58,30 +78,32 @@ fn test_update_listing() {
 }
 
 #[test]
-#[should_panic(expected = "Only the owner can update the listing")]
 fn test_update_listing_unauthorized() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
     let unauthorized = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
     let new_data_hash = symbol_short!("HASH2");
 
     client.create_listing(&id, &data_hash, &owner);
-    client.update_listing(&id, &new_data_hash, &unauthorized);
+    let result = client.try_update_listing(&id, &new_data_hash, &unauthorized);
+    assert_eq!(result, Err(Ok(ListingError::NotOwner)));
 }
 
 #[test]
 fn test_update_status() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
 
     client.create_listing(&id, &data_hash, &owner);
@@ -94,29 +116,31 @@ fn test_update_status() {
 }
 
 #[test]
-#[should_panic(expected = "Only the owner can update the status")]
 fn test_update_status_unauthorized() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
     let unauthorized = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
 
     client.create_listing(&id, &data_hash, &owner);
-    client.update_status(&id, &unauthorized, &PropertyStatus::Booked);
+    let result = client.try_update_status(&id, &unauthorized, &PropertyStatus::Booked);
+    assert_eq!(result, Err(Ok(ListingError::NotOwner)));
 }
 
 #[test]
 fn test_get_listing() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
     let owner = Address::generate(&env);
-    let id = symbol_short!("PROP1");
+    let id = property_id(&env, "PROP1");
     let data_hash = symbol_short!("HASH1");
 
     client.create_listing(&id, &data_hash, &owner);
@@ -129,12 +153,463 @@ fn test_get_listing() {
 }
 
 #[test]
-#[should_panic(expected = "Property listing not found")]
 fn test_get_nonexistent_listing() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, PropertyListingContract);
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let id = property_id(&env, "PROP1");
+    let result = client.try_get_listing(&id);
+    assert_eq!(result, Err(Ok(ListingError::NotFound)));
+}
+
+#[test]
+fn test_update_nonexistent_listing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+    let data_hash = symbol_short!("HASH1");
+
+    let result = client.try_update_listing(&id, &data_hash, &owner);
+    assert_eq!(result, Err(Ok(ListingError::NotFound)));
+
+    let result = client.try_update_status(&id, &owner, &PropertyStatus::Maintenance);
+    assert_eq!(result, Err(Ok(ListingError::NotFound)));
+}
+
+#[test]
+fn test_get_listings_pagination() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let data_hash = symbol_short!("HASH1");
+
+    assert_eq!(client.get_listing_count(), 0);
+    assert_eq!(client.get_listings(&0, &10).len(), 0);
+
+    client.create_listing(&property_id(&env, "PROP1"), &data_hash, &owner);
+    client.create_listing(&property_id(&env, "PROP2"), &data_hash, &owner);
+    client.create_listing(&property_id(&env, "PROP3"), &data_hash, &owner);
+    assert_eq!(client.get_listing_count(), 3);
+
+    let page = client.get_listings(&0, &2);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get(0).unwrap().id, property_id(&env, "PROP1"));
+    assert_eq!(page.get(1).unwrap().id, property_id(&env, "PROP2"));
+
+    let page = client.get_listings(&2, &2);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().id, property_id(&env, "PROP3"));
+
+    // Offsets past the end return an empty page
+    assert_eq!(client.get_listings(&3, &2).len(), 0);
+    assert_eq!(client.get_listings(&u32::MAX, &u32::MAX).len(), 0);
+}
+
+#[test]
+fn test_get_listings_page_size_capped() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let data_hash = symbol_short!("HASH1");
+
+    for i in 0..(MAX_PAGE_SIZE + 5) {
+        let id = property_id(&env, &std::format!("PROP{}", i));
+        client.create_listing(&id, &data_hash, &owner);
+    }
+
+    assert_eq!(client.get_listing_count(), MAX_PAGE_SIZE + 5);
+    assert_eq!(client.get_listings(&0, &1000).len(), MAX_PAGE_SIZE);
+    assert_eq!(client.get_listings(&MAX_PAGE_SIZE, &1000).len(), 5);
+}
+
+#[test]
+fn test_remove_listing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let data_hash = symbol_short!("HASH1");
+
+    client.create_listing(&property_id(&env, "PROP1"), &data_hash, &owner);
+    client.create_listing(&property_id(&env, "PROP2"), &data_hash, &owner);
+    client.create_listing(&property_id(&env, "PROP3"), &data_hash, &owner);
+
+    client.remove_listing(&property_id(&env, "PROP1"), &owner);
+
+    assert_eq!(client.get_listing_count(), 2);
+    assert_eq!(
+        client.try_get_listing(&property_id(&env, "PROP1")),
+        Err(Ok(ListingError::NotFound))
+    );
+
+    // The last listing takes the removed slot
+    let page = client.get_listings(&0, &10);
+    assert_eq!(page.len(), 2);
+    assert_eq!(page.get(0).unwrap().id, property_id(&env, "PROP3"));
+    assert_eq!(page.get(1).unwrap().id, property_id(&env, "PROP2"));
+
+    // The removed ID can be registered again
+    client.create_listing(&property_id(&env, "PROP1"), &data_hash, &owner);
+    assert_eq!(client.get_listing_count(), 3);
+    assert_eq!(
+        client.get_listings(&2, &1).get(0).unwrap().id,
+        property_id(&env, "PROP1")
+    );
+}
+
+#[test]
+fn test_remove_listing_unauthorized() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let unauthorized = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+
+    client.create_listing(&id, &symbol_short!("HASH1"), &owner);
+
+    let result = client.try_remove_listing(&id, &unauthorized);
+    assert_eq!(result, Err(Ok(ListingError::NotOwner)));
+    assert_eq!(client.get_listing_count(), 1);
+
+    let result = client.try_remove_listing(&property_id(&env, "PROP2"), &owner);
+    assert_eq!(result, Err(Ok(ListingError::NotFound)));
+}
+
+#[test]
+fn test_create_listing_requires_owner_auth() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+    let data_hash = symbol_short!("HASH1");
+
+    client.create_listing(&id, &data_hash, &owner);
+    assert_eq!(
+        env.auths(),
+        std::vec![(
+            owner.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    contract_id.clone(),
+                    Symbol::new(&env, "create_listing"),
+                    (id.clone(), data_hash.clone(), owner.clone()).into_val(&env),
+                )),
+                sub_invocations: std::vec![],
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_create_listing_without_auth_rejected() {
+    let env = Env::default();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+
+    // No signature from the owner
+    let result = client.try_create_listing(&id, &symbol_short!("HASH1"), &owner);
+    assert!(result.is_err());
+    assert_eq!(client.get_listing_count(), 0);
+}
+
+#[test]
+fn test_mutations_reject_impersonation() {
+    let env = Env::default();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+    let data_hash = symbol_short!("HASH1");
+    let new_data_hash = symbol_short!("HASH2");
+
+    client
+        .mock_auths(&[MockAuth {
+            address: &owner,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "create_listing",
+                args: (id.clone(), data_hash.clone(), owner.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .create_listing(&id, &data_hash, &owner);
+
+    // The attacker signs but passes the owner's address
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "update_listing",
+                args: (id.clone(), new_data_hash.clone(), owner.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_update_listing(&id, &new_data_hash, &owner);
+    assert!(result.is_err());
+
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "update_status",
+                args: (id.clone(), owner.clone(), PropertyStatus::Maintenance).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_update_status(&id, &owner, &PropertyStatus::Maintenance);
+    assert!(result.is_err());
+
+    let result = client
+        .mock_auths(&[MockAuth {
+            address: &attacker,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "remove_listing",
+                args: (id.clone(), owner.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .try_remove_listing(&id, &owner);
+    assert!(result.is_err());
+
+    // The listing is unchanged
+    let listing = client.get_listing(&id);
+    assert_eq!(listing.data_hash, data_hash);
+    assert_eq!(listing.status, PropertyStatus::Available);
+    assert_eq!(client.get_listing_count(), 1);
+
+    // The owner's own signature is accepted
+    let updated_listing = client
+        .mock_auths(&[MockAuth {
+            address: &owner,
+            invoke: &MockAuthInvoke {
+                contract: &contract_id,
+                fn_name: "update_listing",
+                args: (id.clone(), new_data_hash.clone(), owner.clone()).into_val(&env),
+                sub_invokes: &[],
+            },
+        }])
+        .update_listing(&id, &new_data_hash, &owner);
+    assert_eq!(updated_listing.data_hash, new_data_hash);
+}
+
+// Reads the remaining TTL of a persistent entry in the contract's storage
+fn persistent_ttl(env: &Env, contract_id: &Address, key: &DataKey) -> u32 {
+    env.as_contract(contract_id, || env.storage().persistent().get_ttl(key))
+}
+
+#[test]
+fn test_listing_survives_past_default_ttl() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+    let data_hash = symbol_short!("HASH1");
+    client.create_listing(&id, &data_hash, &owner);
+
+    // Writes bump the listing well past the network's minimum persistent TTL
+    let ttl = persistent_ttl(&env, &contract_id, &DataKey::Listing(id.clone()));
+    assert_eq!(ttl, BUMP_AMOUNT);
+    assert!(ttl > env.ledger().get().min_persistent_entry_ttl);
+
+    // Advance well beyond the default TTL
+    env.ledger().with_mut(|li| {
+        li.sequence_number += 20 * DAY_IN_LEDGERS;
+    });
+
+    let listing = client.get_listing(&id);
+    assert_eq!(listing.data_hash, data_hash);
+    assert_eq!(client.get_listings(&0, &10).len(), 1);
+    assert_eq!(client.get_listing_count(), 1);
+}
+
+#[test]
+fn test_extend_listing_ttl() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let id = property_id(&env, "PROP1");
+    let data_hash = symbol_short!("HASH1");
+    client.create_listing(&id, &data_hash, &owner);
+
+    env.ledger().with_mut(|li| {
+        li.sequence_number += 20 * DAY_IN_LEDGERS;
+    });
+    let listing_key = DataKey::Listing(id.clone());
+    assert_eq!(
+        persistent_ttl(&env, &contract_id, &listing_key),
+        BUMP_AMOUNT - 20 * DAY_IN_LEDGERS
+    );
+
+    // Anyone can extend the listing, without a signature
+    env.set_auths(&[]);
+    client.extend_listing_ttl(&id);
+    for key in [
+        listing_key,
+        DataKey::IndexOf(id.clone()),
+        DataKey::IndexAt(0),
+    ] {
+        assert_eq!(persistent_ttl(&env, &contract_id, &key), BUMP_AMOUNT);
+    }
+
+    // The listing outlives its original TTL
+    env.ledger().with_mut(|li| {
+        li.sequence_number += 20 * DAY_IN_LEDGERS;
+    });
+    assert_eq!(client.get_listing(&id).data_hash, data_hash);
+    assert_eq!(client.get_listings(&0, &10).len(), 1);
+}
+
+#[test]
+fn test_extend_nonexistent_listing_ttl() {
+    let env = Env::default();
+    let contract_id = env.register(PropertyListingContract, ());
     let client = PropertyListingContractClient::new(&env, &contract_id);
 
-    let id = symbol_short!("PROP1");
-    client.get_listing(&id);
+    let result = client.try_extend_listing_ttl(&property_id(&env, "NONE"));
+    assert_eq!(result, Err(Ok(ListingError::NotFound)));
+}
+
+// Writes a listing the way the contract stored it before `PropertyId` keys
+fn store_legacy_listing(
+    env: &Env,
+    contract_id: &Address,
+    legacy_id: &Symbol,
+    owner: &Address,
+    position: Option<u32>,
+) {
+    let legacy = LegacyListing {
+        id: legacy_id.clone(),
+        data_hash: symbol_short!("HASH1"),
+        owner: owner.clone(),
+        status: PropertyStatus::Booked,
+    };
+    env.as_contract(contract_id, || match position {
+        Some(position) => {
+            let storage = env.storage().persistent();
+            storage.set(&LegacyDataKey::Listing(legacy_id.clone()), &legacy);
+            storage.set(&LegacyDataKey::IndexOf(legacy_id.clone()), &position);
+            storage.set(&DataKey::IndexAt(position), legacy_id);
+            env.storage()
+                .instance()
+                .set(&DataKey::Count, &(position + 1));
+        }
+        None => env.storage().instance().set(legacy_id, &legacy),
+    });
+}
+
+#[test]
+fn test_migrate_indexed_listing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let legacy_id = symbol_short!("PROP1");
+    store_legacy_listing(&env, &contract_id, &legacy_id, &owner, Some(0));
+
+    // Unmigrated index entries are skipped rather than breaking enumeration
+    assert_eq!(client.get_listing_count(), 1);
+    assert_eq!(client.get_listings(&0, &10).len(), 0);
+
+    let id = property_id(&env, "PROP1");
+    let listing = client.migrate_listing(&legacy_id, &id);
+    assert_eq!(listing.id, id);
+    assert_eq!(listing.data_hash, symbol_short!("HASH1"));
+    assert_eq!(listing.owner, owner);
+    assert_eq!(listing.status, PropertyStatus::Booked);
+
+    // The listing keeps its index position and the legacy entries are gone
+    assert_eq!(client.get_listing(&id), listing);
+    assert_eq!(client.get_listing_count(), 1);
+    assert_eq!(client.get_listings(&0, &10).get(0).unwrap(), listing);
+    env.as_contract(&contract_id, || {
+        let storage = env.storage().persistent();
+        assert!(!storage.has(&LegacyDataKey::Listing(legacy_id.clone())));
+        assert!(!storage.has(&LegacyDataKey::IndexOf(legacy_id.clone())));
+    });
+
+    // Migrating twice fails
+    let result = client.try_migrate_listing(&legacy_id, &property_id(&env, "PROP2"));
+    assert_eq!(result, Err(Ok(ListingError::NotFound)));
+}
+
+#[test]
+fn test_migrate_instance_listing() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    // Listings from before the index existed are appended to it
+    let owner = Address::generate(&env);
+    client.create_listing(&property_id(&env, "PROP2"), &symbol_short!("HASH2"), &owner);
+    let legacy_id = symbol_short!("PROP1");
+    store_legacy_listing(&env, &contract_id, &legacy_id, &owner, None);
+
+    let id = property_id(&env, "PROP1");
+    let listing = client.migrate_listing(&legacy_id, &id);
+    assert_eq!(client.get_listing_count(), 2);
+    assert_eq!(client.get_listings(&0, &10).get(1).unwrap(), listing);
+    env.as_contract(&contract_id, || {
+        assert!(!env.storage().instance().has(&legacy_id));
+    });
+
+    // Existing IDs can't be overwritten
+    let result = client.try_migrate_listing(&legacy_id, &property_id(&env, "PROP2"));
+    assert_eq!(result, Err(Ok(ListingError::AlreadyExists)));
+}
+
+#[test]
+fn test_migrate_listing_requires_owner_auth() {
+    let env = Env::default();
+    let contract_id = env.register(PropertyListingContract, ());
+    let client = PropertyListingContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let legacy_id = symbol_short!("PROP1");
+    store_legacy_listing(&env, &contract_id, &legacy_id, &owner, Some(0));
+
+    let result = client.try_migrate_listing(&legacy_id, &property_id(&env, "PROP1"));
+    assert!(result.is_err());
+    env.as_contract(&contract_id, || {
+        assert!(env
+            .storage()
+            .persistent()
+            .has(&LegacyDataKey::Listing(legacy_id.clone())));
+    });
 }